@@ -0,0 +1,207 @@
+use crate::fileinfo::FileInfo;
+use crate::formatter::Formatter;
+use crate::hasher::Digest;
+use crate::params::{OutputFormat, Params};
+use crate::server::Server;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// the files of two trees matched by content, regardless of their names.
+#[derive(Debug, Default)]
+pub struct Comparison {
+    pub roots: Vec<PathBuf>,
+    /// files of each content found in both trees, as (in a, in b).
+    pub both: Vec<(Vec<FileInfo>, Vec<FileInfo>)>,
+    pub only_a: Vec<FileInfo>,
+    pub only_b: Vec<FileInfo>,
+    /// files that could not be hashed, so whether they have a copy is unknown.
+    pub unreadable: Vec<FileInfo>,
+}
+
+impl Comparison {
+    pub fn run(a: &Path, b: &Path, app_args: &Params) -> Result<()> {
+        let comparison = Self::scan(a, b, app_args)?;
+        comparison.write(std::io::stdout().lock())
+    }
+
+    /// scans both trees in one pass, hashing every file in full.
+    pub fn scan(a: &Path, b: &Path, app_args: &Params) -> Result<Self> {
+        let app_args = Params {
+            command: None,
+            dirs: vec![a.to_path_buf(), b.to_path_buf()],
+            min_size: Some(String::from("0b")),
+            strict: true,
+            format: OutputFormat::Tree,
+            reference: vec![],
+            ..app_args.clone()
+        };
+
+        let roots = app_args.get_directories()?;
+        if roots.len() != 2 || roots[0].starts_with(&roots[1]) || roots[1].starts_with(&roots[0]) {
+            bail!("compare needs two separate trees, neither inside the other");
+        }
+
+        let server = Server::new(app_args);
+        server.start()?;
+
+        let hashes = server
+            .hw_duplicate_set
+            .iter()
+            .flat_map(|sref| {
+                let (_, hash) = *sref.key();
+                sref.value()
+                    .iter()
+                    .map(|file| (file.path.to_path_buf(), hash))
+                    .collect::<Vec<(PathBuf, Digest)>>()
+            })
+            .collect::<HashMap<PathBuf, Digest>>();
+
+        let mut comparison = Self {
+            roots,
+            ..Default::default()
+        };
+
+        for sref in server.sw_duplicate_set.iter() {
+            // NOTE: a size found only once cannot have a copy, so it never got hashed.
+            if let [file] = sref.value().as_slice() {
+                comparison.push_unique(file.clone());
+                continue;
+            }
+
+            let mut contents: HashMap<Digest, Vec<FileInfo>> = HashMap::new();
+            for file in sref.value() {
+                match hashes.get(&*file.path) {
+                    Some(hash) => contents.entry(*hash).or_default().push(file.clone()),
+                    None => comparison.unreadable.push(file.clone()),
+                }
+            }
+
+            for (_, files) in contents {
+                let (in_a, in_b): (Vec<FileInfo>, Vec<FileInfo>) = files
+                    .into_iter()
+                    .partition(|file| file.root_index(&comparison.roots) == Some(0));
+                match (in_a.is_empty(), in_b.is_empty()) {
+                    (false, false) => comparison.both.push((in_a, in_b)),
+                    (false, true) => comparison.only_a.extend(in_a),
+                    (true, _) => comparison.only_b.extend(in_b),
+                }
+            }
+        }
+
+        comparison.sort();
+        Ok(comparison)
+    }
+
+    fn push_unique(&mut self, file: FileInfo) {
+        match file.root_index(&self.roots) {
+            Some(0) => self.only_a.push(file),
+            _ => self.only_b.push(file),
+        }
+    }
+
+    fn sort(&mut self) {
+        let by_path = |a: &FileInfo, b: &FileInfo| a.path.cmp(&b.path);
+        self.only_a.sort_by(by_path);
+        self.only_b.sort_by(by_path);
+        self.unreadable.sort_by(by_path);
+        self.both.iter_mut().for_each(|(in_a, in_b)| {
+            in_a.sort_by(by_path);
+            in_b.sort_by(by_path);
+        });
+        self.both.sort_by(|x, y| x.0[0].path.cmp(&y.0[0].path));
+    }
+
+    pub fn write(&self, mut out: impl Write) -> Result<()> {
+        let relative = |file: &FileInfo| {
+            Formatter::relative_path(file, &self.roots)
+                .1
+                .to_string_lossy()
+                .to_string()
+        };
+        let joined = |files: &[FileInfo]| {
+            files
+                .iter()
+                .map(relative)
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let (a, b) = (self.roots[0].display(), self.roots[1].display());
+
+        writeln!(out, "ONLY IN {a} ({}):", self.only_a.len())?;
+        self.only_a
+            .iter()
+            .try_for_each(|file| writeln!(out, "  {}", relative(file)))?;
+
+        writeln!(out, "\nONLY IN {b} ({}):", self.only_b.len())?;
+        self.only_b
+            .iter()
+            .try_for_each(|file| writeln!(out, "  {}", relative(file)))?;
+
+        writeln!(out, "\nIN BOTH ({}):", self.both.len())?;
+        self.both.iter().try_for_each(|(in_a, in_b)| {
+            writeln!(out, "  {}  ==  {}", joined(in_a), joined(in_b))
+        })?;
+
+        if !self.unreadable.is_empty() {
+            writeln!(out, "\nUNREADABLE ({}):", self.unreadable.len())?;
+            self.unreadable
+                .iter()
+                .try_for_each(|file| writeln!(out, "  {}", file.path.display()))?;
+        }
+
+        writeln!(
+            out,
+            "\n{} contents in both, {} files only in {a}, {} files only in {b}, {} unreadable",
+            self.both.len(),
+            self.only_a.len(),
+            self.only_b.len(),
+            self.unreadable.len()
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Comparison;
+    use crate::params::Params;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn files_are_matched_by_content_regardless_of_names() -> Result<()> {
+        let (old, new) = (TempDir::new()?, TempDir::new()?);
+        fs::create_dir(old.path().join("nested"))?;
+        fs::write(old.path().join("nested/report.txt"), b"quarterly numbers")?;
+        fs::write(new.path().join("renamed.txt"), b"quarterly numbers")?;
+        fs::write(old.path().join("left-behind.txt"), b"never copied")?;
+        // NOTE: same size as the one left behind, so only the hash tells them apart.
+        fs::write(new.path().join("new-only.txt"), b"only the new")?;
+        fs::write(new.path().join("empty"), b"")?;
+
+        let comparison = Comparison::scan(old.path(), new.path(), &Params::default())?;
+        let names = |files: &[crate::fileinfo::FileInfo]| {
+            files
+                .iter()
+                .map(|file| file.path.file_name().unwrap().to_string_lossy().to_string())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(comparison.both.len(), 1);
+        assert_eq!(names(&comparison.both[0].0), vec!["report.txt"]);
+        assert_eq!(names(&comparison.both[0].1), vec!["renamed.txt"]);
+        assert_eq!(names(&comparison.only_a), vec!["left-behind.txt"]);
+        assert_eq!(names(&comparison.only_b), vec!["empty", "new-only.txt"]);
+        assert!(comparison.unreadable.is_empty());
+
+        assert!(
+            Comparison::scan(old.path(), &old.path().join("nested"), &Params::default()).is_err()
+        );
+
+        Ok(())
+    }
+}
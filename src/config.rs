@@ -0,0 +1,56 @@
+use crate::params::Params;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// settings read from `$XDG_CONFIG_HOME/deduplicator/config.toml`, merged with the cli args.
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// paths whose files are never deleted, linked or moved.
+    pub protect: Vec<PathBuf>,
+}
+
+impl Config {
+    pub fn default_path() -> Result<PathBuf> {
+        let config_home = Params::xdg_home("XDG_CONFIG_HOME", ".config")?;
+        Ok(config_home.join("deduplicator/config.toml"))
+    }
+
+    /// the config at `path`, or the defaults when there is none.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let raw = fs::read_to_string(path)?;
+        toml::from_str(&raw).with_context(|| format!("invalid config {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use anyhow::Result;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn config_lists_protected_paths_and_defaults_when_missing() -> Result<()> {
+        let root = TempDir::new()?;
+        let path = root.path().join("config.toml");
+        assert_eq!(Config::load(&path)?, Config::default());
+
+        std::fs::write(&path, "protect = [\"/srv/originals\", \"~/keep\"]\n")?;
+        assert_eq!(
+            Config::load(&path)?.protect,
+            vec![PathBuf::from("/srv/originals"), PathBuf::from("~/keep")]
+        );
+
+        std::fs::write(&path, "protected = []\n")?;
+        assert!(Config::load(&path).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,700 @@
+use crate::fileinfo::FileInfo;
+use crate::formatter::DuplicateGroup;
+use crate::hasher::Digest;
+use crate::journal::Journal;
+use crate::params::{KeepStrategy, Params, SymlinkTarget};
+use crate::quarantine::Quarantine;
+use crate::reflink::Reflink;
+use crate::trash::Trash;
+use anyhow::{anyhow, bail, Result};
+use bytesize::ByteSize;
+use pathdiff::diff_paths;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Delete,
+    HardLink,
+    SymLink(SymlinkTarget),
+    Reflink,
+    Quarantine(Quarantine),
+    Trash(Trash),
+}
+
+#[derive(Debug, Default)]
+pub struct Tally {
+    pub applied: u64,
+    pub skipped: u64,
+    pub failed: u64,
+    pub reclaimed: u64,
+    /// every file left alone & why, repeated in the final report.
+    pub skipped_files: Vec<(PathBuf, String)>,
+}
+
+impl Tally {
+    pub fn skip(&mut self, path: &Path, reason: String) {
+        println!("SKIPPED: {} ({reason})", path.display());
+        self.skipped += 1;
+        self.skipped_files.push((path.to_path_buf(), reason));
+    }
+}
+
+/// a file deliberately left alone, as opposed to an action that failed.
+#[derive(Debug)]
+pub struct Skip(pub String);
+
+impl fmt::Display for Skip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Skip {}
+
+pub struct Executor;
+
+impl Executor {
+    /// index of the file that must survive in `files`, according to the keep strategy.
+    pub fn select_survivor(files: &[FileInfo], keep: KeepStrategy, roots: &[PathBuf]) -> usize {
+        let root_index = |file: &FileInfo| file.root_index(roots).unwrap_or(usize::MAX);
+        let path_len = |file: &FileInfo| file.path.as_os_str().len();
+
+        // NOTE: every strategy falls back to the path so the survivor is deterministic, and
+        // reference, then protected files are the only candidates when there are any.
+        let tier = |file: &FileInfo| (!file.reference, !file.protected);
+        let best_tier = files.iter().map(tier).min();
+        let indexed = files
+            .iter()
+            .enumerate()
+            .filter(|(_, file)| Some(tier(file)) == best_tier);
+        let survivor = match keep {
+            KeepStrategy::Newest => indexed.min_by_key(|&(_, f)| (Reverse(f.modified), &f.path)),
+            KeepStrategy::Oldest => indexed.min_by_key(|&(_, f)| (f.modified, &f.path)),
+            KeepStrategy::ShortestPath => indexed.min_by_key(|&(_, f)| (path_len(f), &f.path)),
+            KeepStrategy::LongestPath => {
+                indexed.min_by_key(|&(_, f)| (Reverse(path_len(f)), &f.path))
+            }
+            KeepStrategy::FirstRoot => indexed.min_by_key(|&(_, f)| (root_index(f), &f.path)),
+            KeepStrategy::Lexicographic => indexed.min_by_key(|&(_, f)| &f.path),
+        };
+
+        survivor.map(|(index, _)| index).unwrap_or_default()
+    }
+
+    /// applies the action to every file of every group but the survivor.
+    pub fn run(groups: &[DuplicateGroup], action: &Action, app_args: &Params) -> Result<()> {
+        let roots = app_args.get_directories()?;
+        let journal = app_args.get_journal()?;
+        let mut tally = Tally::default();
+
+        let outcome = groups.iter().try_for_each(|(hash, files)| {
+            let survivor = &files[Self::select_survivor(files, app_args.keep, &roots)];
+            let others = files
+                .iter()
+                .filter(|file| file.path != survivor.path && !file.reference)
+                .cloned()
+                .collect::<Vec<FileInfo>>();
+
+            println!("\nKEPT: {}", survivor.path.display());
+            if !Self::verify_group(survivor, &others, &mut tally) {
+                return Ok(());
+            }
+
+            others.iter().try_for_each(|file| {
+                Self::apply_and_report(action, *hash, file, survivor, &journal, &mut tally)
+            })
+        });
+
+        Self::print_tally(action, &tally);
+        outcome
+    }
+
+    /// compares every file byte by byte against the survivor, whatever the hashing mode, and
+    /// reports the whole group as aborted on the first difference.
+    pub fn verify_group(survivor: &FileInfo, files: &[FileInfo], tally: &mut Tally) -> bool {
+        let Err(error) = files
+            .iter()
+            .try_for_each(|file| Self::verify_identical(&survivor.path, &file.path))
+        else {
+            return true;
+        };
+
+        println!("ABORTED: group left untouched ({error})");
+        files
+            .iter()
+            .for_each(|file| tally.skip(&file.path, format!("group aborted, {error}")));
+        false
+    }
+
+    pub fn verify_identical(survivor: &Path, file: &Path) -> Result<()> {
+        let open = |path| fs::File::open(path).map(|f| BufReader::with_capacity(1 << 16, f));
+        let (mut expected, mut actual) = (open(survivor)?, open(file)?);
+
+        loop {
+            let (left, right) = (expected.fill_buf()?, actual.fill_buf()?);
+            let length = left.len().min(right.len());
+
+            if length == 0 {
+                return match left.len() == right.len() {
+                    true => Ok(()),
+                    false => bail!("{} differs in length from the kept file", file.display()),
+                };
+            }
+            if left[..length] != right[..length] {
+                bail!("{} differs from the kept file", file.display());
+            }
+
+            expected.consume(length);
+            actual.consume(length);
+        }
+    }
+
+    pub fn print_tally(action: &Action, tally: &Tally) {
+        println!(
+            "\n{} files {}, {} skipped, {} failed, {} reclaimed.",
+            tally.applied,
+            Self::past_tense(action),
+            tally.skipped,
+            tally.failed,
+            ByteSize::b(tally.reclaimed)
+        );
+
+        if !tally.skipped_files.is_empty() {
+            println!("\nSkipped files:");
+            for (path, reason) in &tally.skipped_files {
+                println!("  {} ({reason})", path.display());
+            }
+        }
+    }
+
+    pub fn apply_and_report(
+        action: &Action,
+        hash: Digest,
+        file: &FileInfo,
+        survivor: &FileInfo,
+        journal: &Journal,
+        tally: &mut Tally,
+    ) -> Result<()> {
+        // NOTE: moving a file frees nothing, neither does dropping one of several links.
+        let frees_space = !matches!(action, Action::Quarantine(_) | Action::Trash(_))
+            && Self::link_count(&file.path).is_ok_and(|links| links == 1);
+
+        match Self::apply(action, hash, file, survivor) {
+            Ok(destination) => {
+                tally.applied += 1;
+                if frees_space {
+                    tally.reclaimed += file.size;
+                }
+                match action {
+                    Action::Delete => println!("DELETED: {}", file.path.display()),
+                    Action::Trash(_) => println!("TRASHED: {}", file.path.display()),
+                    Action::Quarantine(quarantine) => println!(
+                        "QUARANTINED: {} -> {}",
+                        file.path.display(),
+                        quarantine.directory.display()
+                    ),
+                    Action::HardLink | Action::SymLink(_) | Action::Reflink => println!(
+                        "{}: {} -> {}",
+                        Self::past_tense(action).to_uppercase(),
+                        file.path.display(),
+                        survivor.path.display()
+                    ),
+                }
+
+                // NOTE: an action `undo` cannot see must not be followed by any other.
+                journal
+                    .record(action, hash, file, destination)
+                    .map_err(|error| {
+                        anyhow!(
+                            "unable to journal {}, stopping before any further action ({error})",
+                            file.path.display()
+                        )
+                    })
+            }
+            Err(error) if error.is::<Skip>() => {
+                tally.skip(&file.path, error.to_string());
+                Ok(())
+            }
+            Err(error) => {
+                tally.failed += 1;
+                println!("FAILED: {} ({error})", file.path.display());
+                Ok(())
+            }
+        }
+    }
+
+    /// returns where the file went, or what it now refers to, for the journal.
+    pub fn apply(
+        action: &Action,
+        hash: Digest,
+        file: &FileInfo,
+        survivor: &FileInfo,
+    ) -> Result<Option<PathBuf>> {
+        // NOTE: last line of defence, the survivor itself is never touched.
+        if Self::same_path(&file.path, &survivor.path) {
+            bail!("refusing to act on the surviving file");
+        }
+        if Self::same_file(file, survivor) {
+            return Err(Skip(String::from("same inode as the kept file")).into());
+        }
+
+        if file.protected {
+            return Err(Skip(String::from("protected path")).into());
+        }
+        if file.reference {
+            return Err(Skip(String::from("reference file")).into());
+        }
+
+        // NOTE: the file may have been replaced or rewritten since it was scanned.
+        if let Some(changed) = file.changed_since_scan()? {
+            return Err(Skip(format!("{changed} changed since the scan")).into());
+        }
+
+        let survivor_path = Some(survivor.path.to_path_buf());
+        Ok(match action {
+            Action::Delete => fs::remove_file(&file.path).map(|_| None)?,
+            Action::HardLink => Self::hard_link(file, survivor).map(|_| survivor_path)?,
+            Action::SymLink(target) => {
+                Self::sym_link(file, survivor, *target).map(|_| survivor_path)?
+            }
+            Action::Reflink => {
+                Reflink::dedupe(&survivor.path, &file.path, file.size).map(|_| survivor_path)?
+            }
+            Action::Quarantine(quarantine) => Some(quarantine.store(file, hash)?),
+            Action::Trash(trash) => Some(trash.send(&file.path)?),
+        })
+    }
+
+    #[cfg(unix)]
+    fn link_count(path: &Path) -> Result<u64> {
+        use std::os::unix::fs::MetadataExt;
+        Ok(fs::metadata(path)?.nlink())
+    }
+
+    #[cfg(not(unix))]
+    fn link_count(_: &Path) -> Result<u64> {
+        Ok(1)
+    }
+
+    fn hard_link(file: &FileInfo, survivor: &FileInfo) -> Result<()> {
+        let (file_meta, survivor_meta) = (fs::metadata(&file.path)?, fs::metadata(&survivor.path)?);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            if file_meta.dev() != survivor_meta.dev() {
+                bail!("refusing to hardlink across filesystem boundaries");
+            }
+            if file_meta.ino() == survivor_meta.ino() {
+                return Err(Skip(String::from("already hardlinked to the kept file")).into());
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (file_meta, survivor_meta);
+
+        Self::replace_atomically(&file.path, |temp| fs::hard_link(&survivor.path, temp))
+    }
+
+    /// what a symbolic link replacing `file` should contain to point at the survivor.
+    pub fn link_target(
+        file: &FileInfo,
+        survivor: &FileInfo,
+        target: SymlinkTarget,
+    ) -> Result<PathBuf> {
+        match target {
+            SymlinkTarget::Absolute => Ok(survivor.path.to_path_buf()),
+            SymlinkTarget::Relative => file
+                .path
+                .parent()
+                .and_then(|parent| diff_paths(&survivor.path, parent))
+                .ok_or_else(|| anyhow!("unable to compute a relative link target")),
+        }
+    }
+
+    fn sym_link(file: &FileInfo, survivor: &FileInfo, target: SymlinkTarget) -> Result<()> {
+        let link_target = Self::link_target(file, survivor, target)?;
+
+        let expected = fs::canonicalize(&survivor.path)?;
+        let resolves_to_survivor =
+            |link: &Path| fs::canonicalize(link).is_ok_and(|resolved| resolved == expected);
+
+        let temp = Self::temp_sibling(&file.path)?;
+        Self::symlink(&link_target, &temp)?;
+
+        // NOTE: check the link before it replaces anything, and once more after the rename.
+        if !resolves_to_survivor(&temp) {
+            let _ = fs::remove_file(&temp);
+            bail!(
+                "link target {} does not resolve to the kept file",
+                link_target.display()
+            );
+        }
+
+        fs::rename(&temp, &file.path).inspect_err(|_| {
+            let _ = fs::remove_file(&temp);
+        })?;
+
+        if !resolves_to_survivor(&file.path) {
+            bail!("replaced by a link that does not resolve to the kept file");
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+        std::os::unix::fs::symlink(target, link)
+    }
+
+    #[cfg(windows)]
+    fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+
+    /// builds the replacement next to the target under a temporary name, then renames it over
+    /// the target so the path never goes missing.
+    pub fn replace_atomically(
+        target: &Path,
+        create: impl FnOnce(&Path) -> std::io::Result<()>,
+    ) -> Result<()> {
+        let temp = Self::temp_sibling(target)?;
+        create(&temp)?;
+
+        if let Err(error) = fs::rename(&temp, target) {
+            let _ = fs::remove_file(&temp);
+            return Err(error.into());
+        }
+
+        Ok(())
+    }
+
+    /// renames `from` to `to`, falling back to copy & remove when they live on different
+    /// filesystems.
+    pub fn move_file(from: &Path, to: &Path) -> Result<()> {
+        match fs::rename(from, to) {
+            Ok(_) => Ok(()),
+            Err(error) if error.kind() == ErrorKind::CrossesDevices => {
+                let modified = fs::metadata(from)?.modified()?;
+                fs::copy(from, to)?;
+                fs::File::options()
+                    .write(true)
+                    .open(to)?
+                    .set_modified(modified)?;
+                fs::remove_file(from).inspect_err(|_| {
+                    let _ = fs::remove_file(to);
+                })?;
+                Ok(())
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn temp_sibling(target: &Path) -> Result<PathBuf> {
+        let (Some(parent), Some(name)) = (target.parent(), target.file_name()) else {
+            bail!("unable to determine the parent directory");
+        };
+
+        let suffix: u32 = rand::rng().random();
+        Ok(parent.join(format!(
+            ".{}.deduplicator-{suffix:08x}",
+            name.to_string_lossy()
+        )))
+    }
+
+    /// whether the file is the survivor under another path, acting on it would take the
+    /// survivor along. symlinked dirs, bind mounts, overlapping roots & hardlinks all do that.
+    pub fn same_file(file: &FileInfo, survivor: &FileInfo) -> bool {
+        Self::same_path(&file.path, &survivor.path)
+            || (file.dev, file.ino) != (0, 0)
+                && (file.dev, file.ino) == (survivor.dev, survivor.ino)
+    }
+
+    fn same_path(a: &Path, b: &Path) -> bool {
+        a == b
+            || matches!(
+                (std::fs::canonicalize(a), std::fs::canonicalize(b)),
+                (Ok(a), Ok(b)) if a == b
+            )
+    }
+
+    pub fn past_tense(action: &Action) -> &'static str {
+        match action {
+            Action::Delete => "deleted",
+            Action::HardLink => "hardlinked",
+            Action::SymLink(_) => "symlinked",
+            Action::Reflink => "reflinked",
+            Action::Quarantine(_) => "quarantined",
+            Action::Trash(_) => "trashed",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Executor, Skip};
+    use crate::fileinfo::{FileInfo, FileState};
+    use crate::hasher::Digest;
+    use crate::params::{KeepStrategy, Params, SymlinkTarget};
+    use anyhow::Result;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime};
+    use tempfile::TempDir;
+
+    fn fileinfo(path: &str, age_secs: u64) -> FileInfo {
+        FileInfo {
+            path: PathBuf::from(path).into_boxed_path(),
+            size: 10,
+            modified: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 - age_secs),
+            dev: 0,
+            ino: 0,
+            protected: false,
+            reference: false,
+            state: Arc::new(Mutex::new(FileState::Unprocessed)),
+        }
+    }
+
+    #[test]
+    fn keep_strategies_select_the_expected_survivor() {
+        let files = [
+            fileinfo("/b/second/copy.txt", 30),
+            fileinfo("/a/x.txt", 10),
+            fileinfo("/b/first.txt", 20),
+        ];
+        let roots = [PathBuf::from("/b"), PathBuf::from("/a")];
+        let survivor = |keep| Executor::select_survivor(&files, keep, &roots);
+
+        assert_eq!(survivor(KeepStrategy::Newest), 1);
+        assert_eq!(survivor(KeepStrategy::Oldest), 0);
+        assert_eq!(survivor(KeepStrategy::ShortestPath), 1);
+        assert_eq!(survivor(KeepStrategy::LongestPath), 0);
+        assert_eq!(survivor(KeepStrategy::FirstRoot), 2);
+        assert_eq!(survivor(KeepStrategy::Lexicographic), 1);
+
+        let mut flagged = files.clone();
+        flagged[2].protected = true;
+        assert_eq!(
+            Executor::select_survivor(&flagged, KeepStrategy::Newest, &roots),
+            2
+        );
+
+        flagged[0].reference = true;
+        assert_eq!(
+            Executor::select_survivor(&flagged, KeepStrategy::Newest, &roots),
+            0
+        );
+    }
+
+    #[test]
+    fn delete_keeps_exactly_one_file_per_group() -> Result<()> {
+        let (root, state) = (TempDir::new()?, TempDir::new()?);
+        let files = FileInfo::create_copies(
+            root.path(),
+            &["one.txt", "two.txt", "three.txt"],
+            b"same content",
+        )?;
+
+        let params = Params {
+            dirs: vec![root.path().into()],
+            journal: Some(state.path().join("journal.jsonl")),
+            keep: KeepStrategy::Lexicographic,
+            ..Default::default()
+        };
+
+        Executor::run(&[(Digest::from(1), files)], &Action::Delete, &params)?;
+
+        let remaining = fs::read_dir(root.path())?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].file_name(), "one.txt");
+        assert_eq!(params.get_journal()?.pending()?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn nothing_else_is_touched_once_the_journal_cannot_be_written() -> Result<()> {
+        let (root, state) = (TempDir::new()?, TempDir::new()?);
+        let files = FileInfo::create_copies(
+            root.path(),
+            &["one.txt", "two.txt", "three.txt"],
+            b"same content",
+        )?;
+        // NOTE: a plain file where the journal's directory should be.
+        fs::write(state.path().join("blocked"), b"")?;
+
+        let params = Params {
+            dirs: vec![root.path().into()],
+            journal: Some(state.path().join("blocked/journal.jsonl")),
+            keep: KeepStrategy::Lexicographic,
+            ..Default::default()
+        };
+
+        let outcome = Executor::run(
+            &[(Digest::from(1), files.clone())],
+            &Action::Delete,
+            &params,
+        );
+
+        assert!(outcome.is_err_and(|error| error.to_string().contains("unable to journal")));
+        assert_eq!(
+            files.iter().filter(|file| file.path.exists()).count(),
+            2,
+            "only the first duplicate may be deleted"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn groups_are_aborted_when_contents_differ_past_the_first_pages() -> Result<()> {
+        let (root, state) = (TempDir::new()?, TempDir::new()?);
+        let files = [b'a', b'b']
+            .iter()
+            .enumerate()
+            .map(|(index, tail)| {
+                let path = root.path().join(format!("{index}.bin"));
+                let mut content = vec![0u8; 65536];
+                content.push(*tail);
+                File::create_new(&path)?.write_all(&content)?;
+                FileInfo::new(path)
+            })
+            .collect::<Result<Vec<FileInfo>>>()?;
+
+        let params = Params {
+            dirs: vec![root.path().into()],
+            journal: Some(state.path().join("journal.jsonl")),
+            ..Default::default()
+        };
+
+        Executor::run(
+            &[(Digest::from(1), files.clone())],
+            &Action::Delete,
+            &params,
+        )?;
+
+        assert!(files.iter().all(|file| file.path.exists()));
+        assert!(Executor::verify_identical(&files[0].path, &files[1].path).is_err());
+        assert!(Executor::verify_identical(&files[0].path, &files[0].path).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn files_changed_since_the_scan_are_skipped() -> Result<()> {
+        let root = TempDir::new()?;
+        let files = FileInfo::create_copies(root.path(), &["one.txt", "two.txt"], b"same content")?;
+
+        // NOTE: same size & mtime, only the inode gives the swap away.
+        let replacement = root.path().join("replacement.txt");
+        fs::copy(&files[1].path, &replacement)?;
+        File::options()
+            .write(true)
+            .open(&replacement)?
+            .set_modified(files[1].modified)?;
+        fs::rename(&replacement, &files[1].path)?;
+
+        let result = Executor::apply(&Action::Delete, Digest::from(1), &files[1], &files[0]);
+
+        assert!(result.is_err_and(|error| error.is::<Skip>()));
+        assert!(files[1].path.exists());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_link_replaces_duplicates_with_links_to_the_survivor() -> Result<()> {
+        use std::os::unix::fs::MetadataExt;
+
+        let root = TempDir::new()?;
+        let files = FileInfo::create_copies(root.path(), &["one.txt", "two.txt"], b"same content")?;
+
+        Executor::apply(&Action::HardLink, Digest::from(1), &files[1], &files[0])?;
+
+        let (one, two) = (fs::metadata(&files[0].path)?, fs::metadata(&files[1].path)?);
+        assert_eq!(one.ino(), two.ino());
+        assert_eq!(fs::read(&files[1].path)?, b"same content");
+        assert_eq!(fs::read_dir(root.path())?.count(), 2);
+
+        let relinked = Executor::apply(&Action::HardLink, Digest::from(1), &files[1], &files[0]);
+        assert!(relinked.is_err_and(|error| error.is::<Skip>()));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sym_link_points_duplicates_at_the_survivor() -> Result<()> {
+        let root = TempDir::new()?;
+        fs::create_dir(root.path().join("nested"))?;
+        let files = FileInfo::create_copies(
+            root.path(),
+            &["kept.txt", "nested/relative.txt", "nested/absolute.txt"],
+            b"same content",
+        )?;
+
+        let relative = Action::SymLink(SymlinkTarget::Relative);
+        let absolute = Action::SymLink(SymlinkTarget::Absolute);
+        Executor::apply(&relative, Digest::from(1), &files[1], &files[0])?;
+        Executor::apply(&absolute, Digest::from(1), &files[2], &files[0])?;
+
+        assert_eq!(fs::read_link(&files[1].path)?, PathBuf::from("../kept.txt"));
+        assert_eq!(fs::read_link(&files[2].path)?, files[0].path.to_path_buf());
+        assert_eq!(fs::read(&files[1].path)?, b"same content");
+        assert_eq!(fs::read_dir(root.path().join("nested"))?.count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reflink_shares_extents_or_explains_why_not() -> Result<()> {
+        let root = TempDir::new()?;
+        let files = FileInfo::create_copies(root.path(), &["one.bin", "two.bin"], &[9u8; 65536])?;
+
+        // NOTE: depends on the filesystem backing the tempdir (btrfs & xfs support it).
+        if let Err(error) = Executor::apply(&Action::Reflink, Digest::from(1), &files[1], &files[0])
+        {
+            assert!(error.to_string().contains("support reflinks"), "{error}");
+        }
+
+        assert_eq!(fs::read(&files[1].path)?, vec![9u8; 65536]);
+        assert_eq!(fs::metadata(&files[1].path)?.modified()?, files[1].modified);
+
+        Ok(())
+    }
+
+    #[test]
+    fn apply_refuses_to_touch_the_survivor() -> Result<()> {
+        let root = TempDir::new()?;
+        let path = root.path().join("only.txt");
+        File::create_new(&path)?.write_all(b"content")?;
+        let file = FileInfo::new(path.clone())?;
+
+        assert!(Executor::apply(&Action::Delete, Digest::from(1), &file, &file).is_err());
+        assert!(path.exists());
+
+        // NOTE: the same inode under another path, as a bind mount would show it.
+        #[cfg(unix)]
+        {
+            let alias = root.path().join("alias.txt");
+            fs::hard_link(&path, &alias)?;
+            let alias = FileInfo::new(alias)?;
+
+            let result = Executor::apply(&Action::Delete, Digest::from(1), &alias, &file);
+            assert!(result.is_err_and(|error| error.is::<Skip>()));
+            assert!(alias.path.exists());
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
+use crate::hasher::{Digest, HashAlgorithm};
 use anyhow::Result;
-use gxhash::gxhash128;
 use memmap2::Mmap;
 use std::{
     fs,
@@ -20,43 +20,110 @@ pub struct FileInfo {
     pub path: Box<Path>,
     pub size: u64,
     pub modified: SystemTime,
+    /// device & inode at scan time, zero where the platform has none.
+    pub dev: u64,
+    pub ino: u64,
+    /// under a `--protect`ed path: may be kept, never deleted, linked or moved.
+    pub protected: bool,
+    /// under a `--reference` root: only ever shown as the original of a group.
+    pub reference: bool,
     pub state: Arc<Mutex<FileState>>,
 }
 
-impl FileInfo {
-    pub fn hash(&self, seed: i64) -> Result<u128> {
-        if self.size == 0 {
-            return Ok(0u128);
-        };
+/// bytes fed to the hasher at once, so the order of the chunks shapes the digest.
+const CHUNK_SIZE: usize = 65536;
 
-        let file = fs::File::open(&self.path)?;
-        let mapper = unsafe { Mmap::map(&file)? };
-        let content_hash = mapper
-            .chunks(4096)
-            .fold(0u128, |acc, chunk: &[u8]| acc ^ gxhash128(chunk, seed));
+/// bytes hashed in fast mode, digests of larger files only cover this much.
+pub const INIT_PAGES_SIZE: u64 = 16384;
 
-        // NOTE: avoids collision bw an empty file & a file full of null bytes.
-        Ok(content_hash ^ gxhash128(&self.size.to_ne_bytes(), seed))
+impl FileInfo {
+    pub fn hash(&self, algorithm: HashAlgorithm, seed: i64) -> Result<Digest> {
+        let mut hasher = algorithm.hasher(seed);
+
+        // NOTE: empty files can't be mapped, their digest is that of no bytes at all.
+        if self.size > 0 {
+            let file = fs::File::open(&self.path)?;
+            let mapper = unsafe { Mmap::map(&file)? };
+            mapper
+                .chunks(CHUNK_SIZE)
+                .for_each(|chunk| hasher.update(chunk));
+        }
+
+        Ok(hasher.finish())
     }
 
-    pub fn initpages_hash(&self, seed: i64) -> Result<u128> {
-        let mut file = fs::File::open(&self.path)?;
-        let mut buffer = [0; 16384];
-        let bytes_read = file.read(&mut buffer)?;
+    /// digest of the first pages only, the full digest for files no larger than them.
+    pub fn initpages_hash(&self, algorithm: HashAlgorithm, seed: i64) -> Result<Digest> {
+        let mut buffer = vec![];
+        fs::File::open(&self.path)?
+            .take(INIT_PAGES_SIZE)
+            .read_to_end(&mut buffer)?;
 
-        Ok(gxhash128(&buffer[..bytes_read], seed))
+        let mut hasher = algorithm.hasher(seed);
+        hasher.update(&buffer);
+        Ok(hasher.finish())
     }
 
     pub fn new(path: PathBuf) -> Result<Self> {
         let filemeta = std::fs::metadata(&path)?;
+        let (dev, ino) = Self::identity(&filemeta);
         Ok(Self {
             path: path.into_boxed_path(),
             size: filemeta.len(),
             modified: filemeta.modified()?,
+            dev,
+            ino,
+            protected: false,
+            reference: false,
             state: Arc::new(Mutex::new(FileState::Unprocessed)),
         })
     }
 
+    pub fn with_protection(mut self, protected: &[PathBuf]) -> Self {
+        self.protected = protected.iter().any(|path| self.path.starts_with(path));
+        self
+    }
+
+    pub fn with_references(mut self, references: &[PathBuf]) -> Self {
+        self.reference = references.iter().any(|path| self.path.starts_with(path));
+        self
+    }
+
+    /// position of the first root containing the file.
+    pub fn root_index(&self, roots: &[PathBuf]) -> Option<usize> {
+        roots.iter().position(|root| self.path.starts_with(root))
+    }
+
+    /// whether the files can be acted on, i.e. are a group worth reporting.
+    pub fn is_duplicate_group(files: &[FileInfo]) -> bool {
+        files.len() > 1 && files.iter().any(|file| !file.reference)
+    }
+
+    #[cfg(unix)]
+    pub fn identity(meta: &fs::Metadata) -> (u64, u64) {
+        use std::os::unix::fs::MetadataExt;
+        (meta.dev(), meta.ino())
+    }
+
+    #[cfg(not(unix))]
+    pub fn identity(_: &fs::Metadata) -> (u64, u64) {
+        (0, 0)
+    }
+
+    /// what, if anything, changed on disk since the file was scanned.
+    pub fn changed_since_scan(&self) -> Result<Option<&'static str>> {
+        let filemeta = fs::metadata(&self.path)?;
+        Ok(if filemeta.len() != self.size {
+            Some("size")
+        } else if filemeta.modified()? != self.modified {
+            Some("modification time")
+        } else if Self::identity(&filemeta) != (self.dev, self.ino) {
+            Some("inode")
+        } else {
+            None
+        })
+    }
+
     pub fn sw_processed(&self) {
         let mut self_state = self.state.lock().unwrap();
         *self_state = FileState::SwProcessed;
@@ -68,6 +135,21 @@ impl FileInfo {
     }
 }
 
+#[cfg(test)]
+impl FileInfo {
+    /// writes the same content to every name under `root`, e.g. to build a duplicate group.
+    pub fn create_copies(root: &Path, names: &[&str], content: &[u8]) -> Result<Vec<Self>> {
+        names
+            .iter()
+            .map(|name| {
+                let path = root.join(name);
+                fs::write(&path, content)?;
+                Self::new(path)
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -97,7 +179,62 @@ mod test {
 
         let seed: i64 = 246910456374;
 
-        assert_ne!(empty_file_info.hash(seed)?, file_with_empty_bytes_info.hash(seed)?);
+        assert_ne!(empty_file_info.hash(HashAlgorithm::Gxhash, seed)?, file_with_empty_bytes_info.hash(HashAlgorithm::Gxhash, seed)?);
+
+        Ok(())
+    }
+
+    fn hash_of(root: &TempDir, name: &str, chunks: &[&[u8]]) -> Result<Digest> {
+        let path = root.path().join(name);
+        File::create_new(&path)?.write_all(&chunks.concat())?;
+        FileInfo::new(path)?.hash(HashAlgorithm::Gxhash, 246910456374)
+    }
+
+    #[test]
+    fn hash_depends_on_the_order_of_chunks() -> Result<()> {
+        let root = TempDir::new()?;
+        let (x, y, z) = ([1u8; 4096], [2u8; 4096], [3u8; 4096]);
+
+        let forward = hash_of(&root, "forward.bin", &[&x, &y, &z])?;
+        assert_ne!(forward, hash_of(&root, "swapped.bin", &[&y, &x, &z])?);
+        assert_ne!(forward, hash_of(&root, "rotated.bin", &[&z, &x, &y])?);
+        assert_eq!(forward, hash_of(&root, "copy.bin", &[&x, &y, &z])?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn hash_differentiates_between_different_pairs_of_identical_chunks() -> Result<()> {
+        let root = TempDir::new()?;
+        let (x, y, z) = ([1u8; 4096], [2u8; 4096], [3u8; 4096]);
+
+        // NOTE: a pair of identical chunks used to cancel out, whatever the chunk.
+        assert_ne!(
+            hash_of(&root, "xx.bin", &[&x, &x, &z])?,
+            hash_of(&root, "yy.bin", &[&y, &y, &z])?
+        );
+        assert_ne!(
+            hash_of(&root, "xxyy.bin", &[&x, &x, &y, &y])?,
+            hash_of(&root, "xyyx.bin", &[&x, &y, &y, &x])?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn digests_of_small_files_are_the_same_in_fast_mode() -> Result<()> {
+        let root = TempDir::new()?;
+        let path = root.path().join("abc.txt");
+        File::create_new(&path)?.write_all(b"abc")?;
+        let file = FileInfo::new(path)?;
+
+        for algorithm in [HashAlgorithm::Gxhash, HashAlgorithm::Sha256] {
+            assert_eq!(file.hash(algorithm, 7)?, file.initpages_hash(algorithm, 7)?);
+        }
+        assert_eq!(
+            file.hash(HashAlgorithm::Sha256, 7)?.to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
 
         Ok(())
     }
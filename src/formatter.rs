@@ -1,28 +1,48 @@
+use crate::hasher::Digest;
+use crate::html::HtmlReport;
+use crate::params::{OutputFormat, SortKey};
+use crate::processor::GroupKey;
+use crate::report::{Report, ReportGroup, ReportLine, REPORT_VERSION};
+use crate::stats::{Stats, Summary};
 use crate::{fileinfo::FileInfo, params::Params};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use pathdiff::diff_paths;
 use rayon::prelude::*;
-use std::sync::atomic::AtomicU64;
-use std::{path::PathBuf, sync::Arc};
+use std::cmp::Reverse;
+use std::sync::mpsc::Receiver;
+use std::{io::Write, path::PathBuf, sync::Arc};
 
 const YELLOW: &str = "\x1b[33m";
 const RESET: &str = "\x1b[0m";
 
+pub type DuplicateGroup = (Digest, Vec<FileInfo>);
+
 pub struct Formatter;
 impl Formatter {
-    pub fn human_path(file: &FileInfo, aargs: &Params, max_path_length: usize) -> Result<String> {
-        let base_directory: PathBuf = aargs.get_directory()?;
-        let relative_path = diff_paths(&file.path, base_directory).unwrap_or_default();
+    /// the path relative to its own root, labelled `[n]` after the root when several were scanned.
+    pub fn human_path(file: &FileInfo, roots: &[PathBuf], max_path_length: usize) -> String {
+        let (root, relative_path) = Self::relative_path(file, roots);
+        let label = match (roots.len(), root) {
+            (1, _) => String::new(),
+            (_, Some(index)) => format!("[{}] ", index + 1),
+            (_, None) => String::from("[-] "),
+        };
 
-        let formatted_path = format!(
-            "{:<0width$}",
+        format!(
+            "{label}{:<0width$}",
             relative_path.to_str().unwrap_or_default().to_string(),
             width = max_path_length
-        );
+        )
+    }
 
-        Ok(formatted_path)
+    /// index of the root containing the file & the path relative to it. files outside every
+    /// root (i.e. reference files) are relative to the first one.
+    pub fn relative_path(file: &FileInfo, roots: &[PathBuf]) -> (Option<usize>, PathBuf) {
+        let root = file.root_index(roots);
+        let base = &roots[root.unwrap_or_default()];
+        (root, diff_paths(&file.path, base).unwrap_or_default())
     }
 
     pub fn human_filesize(file: &FileInfo) -> Result<String> {
@@ -34,47 +54,462 @@ impl Formatter {
         Ok(modified_time.format("%Y-%m-%d %H:%M:%S").to_string())
     }
 
-    pub fn print(raw: Arc<DashMap<u128, Vec<FileInfo>>>, max_path_len: u64, aargs: &Params) {
+    /// orders files inside a group by path (or mtime, then path).
+    pub fn sort_files(files: &mut [FileInfo], sort: SortKey) {
+        match sort {
+            SortKey::Mtime => {
+                files.sort_by(|a, b| (a.modified, &a.path).cmp(&(b.modified, &b.path)))
+            }
+            _ => files.sort_by(|a, b| a.path.cmp(&b.path)),
+        }
+    }
+
+    /// collects the groups with more than one file into a stable order. numeric keys put the
+    /// largest groups first, path & mtime are ascending. ties always fall back to the path of
+    /// the first file in the group.
+    pub fn sorted_groups(
+        raw: &DashMap<GroupKey, Vec<FileInfo>>,
+        sort: SortKey,
+    ) -> Vec<DuplicateGroup> {
+        let mut groups = raw
+            .iter()
+            .filter(|sref| FileInfo::is_duplicate_group(sref.value()))
+            .map(|sref| {
+                let mut files = sref.value().clone();
+                Self::sort_files(&mut files, sort);
+                (sref.key().1, files)
+            })
+            .collect::<Vec<DuplicateGroup>>();
+
+        let first_path = |group: &DuplicateGroup| group.1[0].path.clone();
+        let size = |group: &DuplicateGroup| group.1[0].size;
+        let wasted = |group: &DuplicateGroup| {
+            group
+                .1
+                .iter()
+                .map(|f| f.size)
+                .sum::<u64>()
+                .saturating_sub(size(group))
+        };
+
+        match sort {
+            SortKey::Path => groups.sort_by_cached_key(first_path),
+            SortKey::Wasted => groups.sort_by_cached_key(|g| (Reverse(wasted(g)), first_path(g))),
+            SortKey::Size => groups.sort_by_cached_key(|g| (Reverse(size(g)), first_path(g))),
+            SortKey::Count => groups.sort_by_cached_key(|g| (Reverse(g.1.len()), first_path(g))),
+            SortKey::Mtime => groups.sort_by_cached_key(|g| (g.1[0].modified, first_path(g))),
+        }
+
+        groups
+    }
+
+    pub fn print(
+        raw: Arc<DashMap<GroupKey, Vec<FileInfo>>>,
+        max_path_len: u64,
+        aargs: &Params,
+        stats: &Stats,
+    ) -> Result<()> {
+        // NOTE: groups were already streamed while hashing.
+        if aargs.format == OutputFormat::Ndjson {
+            return Ok(());
+        }
+
+        let groups = Self::sorted_groups(&raw, aargs.sort);
+        let roots = aargs.get_directories()?;
+        let summary = Summary::new(stats, &Self::report_groups(&groups, &roots));
+        match aargs.format {
+            OutputFormat::Tree => {
+                Self::print_tree(&groups, max_path_len, aargs, &roots);
+                println!("{summary}");
+                Ok(())
+            }
+            OutputFormat::Json => Self::print_json(&groups, &roots, summary),
+            OutputFormat::Csv => {
+                Self::write_delimited(std::io::stdout().lock(), &groups, aargs, b',')
+            }
+            OutputFormat::Tsv => {
+                Self::write_delimited(std::io::stdout().lock(), &groups, aargs, b'\t')
+            }
+            OutputFormat::Fdupes => {
+                Self::write_fdupes(std::io::stdout().lock(), &groups, aargs.show_size)
+            }
+            OutputFormat::Html => Self::print_html(&groups, &roots, &summary),
+            OutputFormat::Ndjson => Ok(()),
+        }
+    }
+
+    /// one row per file; fields containing the delimiter, quotes or newlines get quoted.
+    pub fn write_delimited(
+        out: impl Write,
+        groups: &[DuplicateGroup],
+        aargs: &Params,
+        delimiter: u8,
+    ) -> Result<()> {
+        let roots = aargs.get_directories()?;
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_writer(out);
+
+        writer.write_record([
+            "group",
+            "hash",
+            "size",
+            "modified",
+            "root",
+            "relative_path",
+            "absolute_path",
+            "reference",
+        ])?;
+
+        for (gindex, (hash, files)) in groups.iter().enumerate() {
+            let group = ReportGroup::new(*hash, files, &roots);
+            for (file, rfile) in files.iter().zip(group.files) {
+                let (_, relative_path) = Self::relative_path(file, &roots);
+                writer.write_record([
+                    (gindex + 1).to_string(),
+                    group.hash.clone(),
+                    rfile.size.to_string(),
+                    rfile.modified,
+                    rfile.root.unwrap_or_default(),
+                    relative_path.to_string_lossy().to_string(),
+                    rfile.path,
+                    rfile.reference.to_string(),
+                ])?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// mirrors `fdupes -r` (and `-S`): one path per line, groups terminated by a blank line.
+    pub fn write_fdupes(
+        mut out: impl Write,
+        groups: &[DuplicateGroup],
+        show_size: bool,
+    ) -> Result<()> {
+        for (_, files) in groups {
+            if show_size {
+                let size = files.first().map(|f| f.size).unwrap_or_default();
+                // NOTE: fdupes pads the singular form with a space instead of dropping the 's'.
+                writeln!(
+                    out,
+                    "{size} byte{} each:",
+                    if size == 1 { ' ' } else { 's' }
+                )?;
+            }
+
+            // NOTE: reference files are listed untagged, anything after a path breaks fdupes tooling.
+            for file in files {
+                writeln!(out, "{}", file.path.display())?;
+            }
+
+            writeln!(out)?;
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+
+    /// groups arrive in the order they are confirmed, only the files inside each are sorted.
+    pub fn print_ndjson(
+        groups: Receiver<DuplicateGroup>,
+        sort: SortKey,
+        roots: Vec<PathBuf>,
+        stats: Arc<Stats>,
+    ) -> Result<()> {
+        let mut stdout = std::io::stdout().lock();
+        Self::write_ndjson_line(
+            &mut stdout,
+            &ReportLine::Header {
+                version: REPORT_VERSION,
+            },
+        )?;
+
+        let mut streamed = vec![];
+        for (hash, mut files) in groups {
+            Self::sort_files(&mut files, sort);
+            let line = ReportLine::Group(ReportGroup::new(hash, &files, &roots));
+            Self::write_ndjson_line(&mut stdout, &line)?;
+            if let ReportLine::Group(group) = line {
+                streamed.push(group);
+            }
+        }
+
+        // NOTE: the sink closes once hashing is done, so every stage has recorded its stats.
+        let summary = Summary::new(&stats, &streamed);
+        Self::write_ndjson_line(&mut stdout, &ReportLine::Summary(summary))
+    }
+
+    fn write_ndjson_line(out: &mut impl Write, line: &ReportLine) -> Result<()> {
+        serde_json::to_writer(&mut *out, line)?;
+        writeln!(out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn report_groups(groups: &[DuplicateGroup], roots: &[PathBuf]) -> Vec<ReportGroup> {
+        groups
+            .iter()
+            .map(|(hash, files)| ReportGroup::new(*hash, files, roots))
+            .collect()
+    }
+
+    pub fn print_html(
+        groups: &[DuplicateGroup],
+        roots: &[PathBuf],
+        summary: &Summary,
+    ) -> Result<()> {
+        HtmlReport::write(
+            std::io::stdout().lock(),
+            &Self::report_groups(groups, roots),
+            roots,
+            summary,
+        )
+    }
+
+    pub fn print_json(
+        groups: &[DuplicateGroup],
+        roots: &[PathBuf],
+        summary: Summary,
+    ) -> Result<()> {
+        let report = Report::new(Self::report_groups(groups, roots), summary);
+        let mut stdout = std::io::stdout().lock();
+        serde_json::to_writer_pretty(&mut stdout, &report)?;
+        writeln!(stdout)?;
+
+        Ok(())
+    }
+
+    pub fn print_tree(
+        groups: &[DuplicateGroup],
+        max_path_len: u64,
+        aargs: &Params,
+        roots: &[PathBuf],
+    ) {
         print!("{}", "\n".repeat(if aargs.progress { 2 } else { 1 })); // spacing
 
-        if raw.is_empty() {
+        if groups.is_empty() {
             println!("No duplicates found matching your search criteria.");
-        } else {
-            let printed_count: AtomicU64 = AtomicU64::new(0);
-
-            raw.par_iter().for_each(|sref| {
-                if sref.value().len() > 1 {
-                    printed_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                    let mut ostring = format!("{}{:32x}{}\n", YELLOW, sref.key(), RESET);
-                    let subfields = sref
-                        .value()
-                        .par_iter()
-                        .enumerate()
-                        .map(|(i, finfo)| {
-                            let nodechar = if i == sref.value().len() - 1 {
-                                "└─"
-                            } else {
-                                "├─"
-                            };
-                            format!(
-                                "{}\t{}\t{}\t{}\n",
-                                nodechar,
-                                Self::human_path(finfo, aargs, max_path_len as usize)
-                                    .expect("path formatting failed."),
-                                Self::human_filesize(finfo).expect("filesize formatting failed."),
-                                Self::human_mtime(finfo).expect("modified time formatting failed.")
-                            )
-                        })
-                        .collect::<String>();
-
-                    ostring.push_str(&subfields);
-                    println!("{ostring}");
-                }
-            });
-
-            if printed_count.load(std::sync::atomic::Ordering::Relaxed) < 1 {
-                println!("No duplicates found matching your search criteria.");
-            }
+            return;
+        }
+
+        // NOTE: paths are relative to their own root, the legend maps the labels back.
+        if roots.len() > 1 {
+            roots
+                .iter()
+                .enumerate()
+                .for_each(|(index, root)| println!("[{}] {}", index + 1, root.display()));
+            println!();
         }
+
+        // NOTE: formatting is still parallel, only printing happens in the sorted order.
+        let formatted = groups
+            .par_iter()
+            .map(|(hash, files)| {
+                let mut ostring = format!("{YELLOW}{hash}{RESET}\n");
+                let subfields = files
+                    .iter()
+                    .enumerate()
+                    .map(|(i, finfo)| {
+                        let nodechar = if i == files.len() - 1 {
+                            "└─"
+                        } else {
+                            "├─"
+                        };
+                        format!(
+                            "{}\t{}\t{}\t{}{}\n",
+                            nodechar,
+                            Self::human_path(finfo, roots, max_path_len as usize),
+                            Self::human_filesize(finfo).expect("filesize formatting failed."),
+                            Self::human_mtime(finfo).expect("modified time formatting failed."),
+                            if finfo.reference { "\treference" } else { "" }
+                        )
+                    })
+                    .collect::<String>();
+
+                ostring.push_str(&subfields);
+                ostring
+            })
+            .collect::<Vec<String>>();
+
+        formatted.iter().for_each(|ostring| println!("{ostring}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DuplicateGroup, Formatter};
+    use crate::{
+        fileinfo::FileInfo,
+        hasher::Digest,
+        params::{Params, SortKey},
+    };
+    use anyhow::Result;
+    use dashmap::DashMap;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn duplicate_set(root: &TempDir, names: &[&str]) -> Result<Vec<DuplicateGroup>> {
+        let files = FileInfo::create_copies(root.path(), names, b"duplicate content")?;
+
+        Ok(vec![(Digest::from(42), files)])
+    }
+
+    fn write_files(root: &TempDir, files: &[(&str, usize)]) -> Result<Vec<FileInfo>> {
+        files
+            .iter()
+            .map(|(name, size)| {
+                let path = root.path().join(name);
+                File::create_new(&path)?.write_all(&vec![1u8; *size])?;
+                FileInfo::new(path)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sorted_groups_are_stable_and_ordered_by_the_sort_key() -> Result<()> {
+        let root = TempDir::new()?;
+        let store = DashMap::new();
+        store.insert(
+            (10, Digest::from(1)),
+            write_files(&root, &[("b-small", 10), ("a-small", 10)])?,
+        );
+        store.insert(
+            (1000, Digest::from(2)),
+            write_files(&root, &[("z-large", 1000), ("c-large", 1000)])?,
+        );
+        store.insert(
+            (100, Digest::from(3)),
+            write_files(&root, &[("m-many", 100), ("n-many", 100), ("o-many", 100)])?,
+        );
+        store.insert((5, Digest::from(4)), write_files(&root, &[("single", 5)])?);
+
+        let order = |sort: SortKey| -> Vec<Digest> {
+            Formatter::sorted_groups(&store, sort)
+                .iter()
+                .map(|(hash, _)| *hash)
+                .collect()
+        };
+
+        assert_eq!(order(SortKey::Path), [1, 2, 3].map(Digest::from));
+        assert_eq!(order(SortKey::Wasted), [2, 3, 1].map(Digest::from));
+        assert_eq!(order(SortKey::Size), [2, 3, 1].map(Digest::from));
+        assert_eq!(order(SortKey::Count), [3, 1, 2].map(Digest::from));
+
+        let groups = Formatter::sorted_groups(&store, SortKey::Path);
+        assert!(groups[0].1[0].path.ends_with("a-small"));
+        assert!(groups[1].1[0].path.ends_with("c-large"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn groups_sharing_a_hash_but_not_a_size_stay_apart() -> Result<()> {
+        let root = TempDir::new()?;
+        let store = DashMap::new();
+        store.insert(
+            (10, Digest::from(7)),
+            write_files(&root, &[("a-10", 10), ("b-10", 10)])?,
+        );
+        store.insert(
+            (20, Digest::from(7)),
+            write_files(&root, &[("c-20", 20), ("d-20", 20)])?,
+        );
+
+        let groups = Formatter::sorted_groups(&store, SortKey::Path);
+
+        assert_eq!(groups.len(), 2);
+        assert!(groups
+            .iter()
+            .all(|(_, files)| files.iter().all(|file| file.size == files[0].size)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn delimited_output_quotes_paths_containing_delimiters_and_newlines() -> Result<()> {
+        let root = TempDir::new()?;
+        let params = Params {
+            dirs: vec![root.path().into()],
+            ..Default::default()
+        };
+        let mut store = duplicate_set(&root, &["with,comma", "with\ttab", "with\nnewline"])?;
+        store[0].1[0].reference = true;
+
+        let mut csv_out = vec![];
+        Formatter::write_delimited(&mut csv_out, &store, &params, b',')?;
+        let csv_out = String::from_utf8(csv_out)?;
+
+        assert!(csv_out
+            .starts_with("group,hash,size,modified,root,relative_path,absolute_path,reference\n"));
+        assert!(csv_out.contains(",\"with,comma\","));
+        assert!(csv_out.contains(",with\ttab,"));
+        assert!(csv_out.contains(",\"with\nnewline\","));
+        assert_eq!(csv_out.matches(",true\n").count(), 1);
+
+        let mut tsv_out = vec![];
+        Formatter::write_delimited(&mut tsv_out, &store, &params, b'\t')?;
+        let tsv_out = String::from_utf8(tsv_out)?;
+
+        assert!(tsv_out.contains("\twith,comma\t"));
+        assert!(tsv_out.contains("\t\"with\ttab\"\t"));
+        assert!(tsv_out.contains("\t\"with\nnewline\"\t"));
+
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .from_reader(tsv_out.as_bytes());
+        assert_eq!(reader.records().count(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn paths_are_relative_to_their_own_root() -> Result<()> {
+        let (first, second) = (TempDir::new()?, TempDir::new()?);
+        let files = [&first, &second]
+            .iter()
+            .map(|root| duplicate_set(root, &["photo.jpg"]).map(|mut set| set.remove(0).1))
+            .collect::<Result<Vec<Vec<FileInfo>>>>()?
+            .concat();
+        let roots = Params {
+            dirs: vec![first.path().into(), second.path().into()],
+            ..Default::default()
+        }
+        .get_directories()?;
+
+        assert_eq!(Formatter::human_path(&files[0], &roots, 0), "[1] photo.jpg");
+        assert_eq!(Formatter::human_path(&files[1], &roots, 0), "[2] photo.jpg");
+        assert_eq!(
+            Formatter::human_path(&files[1], &roots[1..], 0),
+            "photo.jpg"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn fdupes_output_separates_groups_with_blank_lines() -> Result<()> {
+        let root = TempDir::new()?;
+        let mut store = duplicate_set(&root, &["one.txt", "two.txt"])?;
+        store[0].1[1].reference = true;
+
+        let mut plain = vec![];
+        Formatter::write_fdupes(&mut plain, &store, false)?;
+        let plain = String::from_utf8(plain)?;
+        let lines = plain.lines().collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[..2].iter().all(|line| line.starts_with('/')));
+        assert!(lines[1].ends_with("two.txt"));
+        assert!(plain.ends_with("\n\n"));
+
+        let mut sized = vec![];
+        Formatter::write_fdupes(&mut sized, &store, true)?;
+
+        assert!(String::from_utf8(sized)?.starts_with("17 bytes each:\n"));
+
+        Ok(())
     }
 }
@@ -0,0 +1,175 @@
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use gxhash::GxHasher;
+use serde::{Deserialize, Serialize};
+use sha2::Digest as _;
+use std::{fmt, hash::Hasher, str::FromStr};
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// Fastest, but seeded per run so digests only compare within a run
+    #[default]
+    Gxhash,
+    /// XXH3 128 bit, matches `xxh128sum`
+    Xxh3,
+    /// BLAKE3, matches `b3sum`
+    Blake3,
+    /// SHA-256, matches `sha256sum`
+    Sha256,
+}
+
+/// a content digest of up to 256 bits, printed as lowercase hex like `sha256sum` & co.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Default)]
+pub struct Digest {
+    bytes: [u8; 32],
+    len: u8,
+}
+
+impl Digest {
+    pub fn new(bytes: &[u8]) -> Self {
+        let mut digest = Self {
+            bytes: [0; 32],
+            len: bytes.len() as u8,
+        };
+        digest.bytes[..bytes.len()].copy_from_slice(bytes);
+        digest
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl From<u128> for Digest {
+    fn from(value: u128) -> Self {
+        Self::new(&value.to_be_bytes())
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_bytes()
+            .iter()
+            .try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl FromStr for Digest {
+    type Err = anyhow::Error;
+
+    fn from_str(hex: &str) -> Result<Self> {
+        // NOTE: checked up front, slicing non-ASCII input by bytes could split a character.
+        if !hex.len().is_multiple_of(2)
+            || hex.len() > 64
+            || !hex.bytes().all(|byte| byte.is_ascii_hexdigit())
+        {
+            bail!("{hex} is not a digest");
+        }
+
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|index| Ok(u8::from_str_radix(&hex[index..index + 2], 16)?))
+            .collect::<Result<Vec<u8>>>()?;
+        Ok(Self::new(&bytes))
+    }
+}
+
+/// streams file contents into a digest, in order.
+pub trait ContentHasher {
+    fn update(&mut self, bytes: &[u8]);
+    fn finish(self: Box<Self>) -> Digest;
+}
+
+impl HashAlgorithm {
+    /// a fresh hasher, only gxhash makes use of the seed.
+    pub fn hasher(self, seed: i64) -> Box<dyn ContentHasher> {
+        match self {
+            Self::Gxhash => Box::new(GxHasher::with_seed(seed)),
+            Self::Xxh3 => Box::new(xxhash_rust::xxh3::Xxh3::new()),
+            Self::Blake3 => Box::new(blake3::Hasher::new()),
+            Self::Sha256 => Box::new(sha2::Sha256::new()),
+        }
+    }
+}
+
+impl ContentHasher for GxHasher {
+    fn update(&mut self, bytes: &[u8]) {
+        self.write(bytes);
+    }
+
+    fn finish(self: Box<Self>) -> Digest {
+        self.finish_u128().into()
+    }
+}
+
+impl ContentHasher for xxhash_rust::xxh3::Xxh3 {
+    fn update(&mut self, bytes: &[u8]) {
+        xxhash_rust::xxh3::Xxh3::update(self, bytes);
+    }
+
+    fn finish(self: Box<Self>) -> Digest {
+        self.digest128().into()
+    }
+}
+
+impl ContentHasher for blake3::Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        blake3::Hasher::update(self, bytes);
+    }
+
+    fn finish(self: Box<Self>) -> Digest {
+        Digest::new(self.finalize().as_bytes())
+    }
+}
+
+impl ContentHasher for sha2::Sha256 {
+    fn update(&mut self, bytes: &[u8]) {
+        sha2::Digest::update(self, bytes);
+    }
+
+    fn finish(self: Box<Self>) -> Digest {
+        Digest::new(&self.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Digest, HashAlgorithm};
+    use anyhow::Result;
+
+    fn digest(algorithm: HashAlgorithm, chunks: &[&[u8]]) -> String {
+        let mut hasher = algorithm.hasher(0);
+        chunks.iter().for_each(|chunk| hasher.update(chunk));
+        hasher.finish().to_string()
+    }
+
+    #[test]
+    fn digests_match_the_reference_tools() -> Result<()> {
+        assert_eq!(
+            digest(HashAlgorithm::Sha256, &[b"a", b"bc"]),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            digest(HashAlgorithm::Blake3, &[b"ab", b"c"]),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        assert_eq!(
+            digest(HashAlgorithm::Xxh3, &[]),
+            "99aa06d3014798d86001c324468d497f"
+        );
+
+        let parsed = "00ff10".parse::<Digest>()?;
+        assert_eq!(parsed.as_bytes(), &[0x00, 0xff, 0x10]);
+        assert_eq!(parsed.to_string(), "00ff10");
+        assert!(["aéb", "+f", "0g"]
+            .iter()
+            .all(|hex| hex.parse::<Digest>().is_err()));
+        assert_eq!(
+            Digest::from(0xabc_u128).to_string(),
+            format!("{:032x}", 0xabc)
+        );
+
+        Ok(())
+    }
+}
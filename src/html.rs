@@ -0,0 +1,251 @@
+use crate::report::ReportGroup;
+use crate::stats::Summary;
+use anyhow::Result;
+use bytesize::ByteSize;
+use chrono::Utc;
+use std::io::Write;
+use std::path::PathBuf;
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 2em; color: #222; }
+h1 { margin-bottom: 0.2em; }
+.meta { color: #666; margin-top: 0; }
+.summary { display: flex; gap: 2em; margin: 1.5em 0; }
+.summary div { background: #f4f4f4; border-radius: 6px; padding: 0.8em 1.2em; }
+.summary strong { display: block; font-size: 1.4em; }
+table { border-collapse: collapse; width: 100%; margin: 0.5em 0 1em; }
+th, td { text-align: left; padding: 0.3em 0.8em; border-bottom: 1px solid #e4e4e4; }
+th { cursor: pointer; user-select: none; background: #fafafa; }
+th::after { content: " \2195"; color: #aaa; }
+td.num, th.num { text-align: right; }
+td.path { font-family: monospace; word-break: break-all; }
+.tag { font-family: sans-serif; font-size: 0.8em; background: #e0ecff; border-radius: 4px; padding: 0 0.4em; margin-left: 0.5em; }
+details { border: 1px solid #e4e4e4; border-radius: 6px; padding: 0.4em 0.8em; margin: 0.5em 0; }
+summary { cursor: pointer; font-family: monospace; }
+"#;
+
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable").forEach(function (table) {
+  table.querySelectorAll("th").forEach(function (header, column) {
+    header.addEventListener("click", function () {
+      var body = table.tBodies[0];
+      var ascending = header.dataset.order !== "asc";
+      header.dataset.order = ascending ? "asc" : "desc";
+      Array.from(body.rows)
+        .sort(function (a, b) {
+          var x = a.cells[column].dataset.sort, y = b.cells[column].dataset.sort;
+          var order = header.classList.contains("num") ? x - y : x.localeCompare(y);
+          return ascending ? order : -order;
+        })
+        .forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});
+"#;
+
+pub struct HtmlReport;
+
+impl HtmlReport {
+    pub fn write(
+        mut out: impl Write,
+        groups: &[ReportGroup],
+        roots: &[PathBuf],
+        summary: &Summary,
+    ) -> Result<()> {
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>Deduplicator Report</title>")?;
+        writeln!(out, "<style>{STYLE}</style>\n</head>\n<body>")?;
+        writeln!(out, "<h1>Deduplicator Report</h1>")?;
+        writeln!(
+            out,
+            "<p class=\"meta\">{} &middot; generated {}</p>",
+            Self::escape(
+                &roots
+                    .iter()
+                    .map(|root| root.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
+        )?;
+
+        Self::write_summary(&mut out, summary)?;
+
+        if groups.is_empty() {
+            writeln!(
+                out,
+                "<p>No duplicates found matching your search criteria.</p>"
+            )?;
+        } else {
+            Self::write_overview(&mut out, groups)?;
+            for (gindex, group) in groups.iter().enumerate() {
+                Self::write_group(&mut out, gindex, group)?;
+            }
+        }
+
+        writeln!(out, "<script>{SCRIPT}</script>\n</body>\n</html>")?;
+        out.flush()?;
+
+        Ok(())
+    }
+
+    fn write_summary(out: &mut impl Write, summary: &Summary) -> Result<()> {
+        let skipped = &summary.files_skipped;
+        let tiles = [
+            (summary.groups.to_string(), "duplicate groups"),
+            (summary.duplicate_files.to_string(), "duplicate files"),
+            (
+                ByteSize::b(summary.reclaimable_bytes).to_string(),
+                "reclaimable",
+            ),
+            (summary.files_scanned.to_string(), "files scanned"),
+            (ByteSize::b(summary.bytes_scanned).to_string(), "scanned"),
+            (skipped.total().to_string(), "files skipped"),
+        ];
+
+        writeln!(out, "<div class=\"summary\">")?;
+        for (value, label) in tiles {
+            writeln!(out, "<div><strong>{value}</strong>{label}</div>")?;
+        }
+        writeln!(out, "</div>")?;
+
+        writeln!(
+            out,
+            "<p class=\"meta\">skipped: {} below min size, {} unreadable, {} walk errors, \
+             {} not regular files, {} hash failures &middot; elapsed: scan {}ms, \
+             sizewise {}ms, hashwise {}ms</p>",
+            skipped.below_min_size,
+            skipped.unreadable,
+            skipped.walk_errors,
+            skipped.not_regular_file,
+            skipped.hash_failures,
+            summary.elapsed_ms.scan,
+            summary.elapsed_ms.sizewise,
+            summary.elapsed_ms.hashwise
+        )?;
+
+        Ok(())
+    }
+
+    fn write_overview(out: &mut impl Write, groups: &[ReportGroup]) -> Result<()> {
+        writeln!(out, "<h2>Groups</h2>\n<table class=\"sortable\">")?;
+        writeln!(
+            out,
+            "<thead><tr><th>hash</th><th class=\"num\">files</th>\
+             <th class=\"num\">size</th><th class=\"num\">wasted</th></tr></thead>\n<tbody>"
+        )?;
+
+        for (gindex, group) in groups.iter().enumerate() {
+            writeln!(
+                out,
+                "<tr><td class=\"path\" data-sort=\"{hash}\"><a href=\"#group-{gindex}\">{hash}</a></td>\
+                 <td class=\"num\" data-sort=\"{count}\">{count}</td>\
+                 <td class=\"num\" data-sort=\"{size}\">{hsize}</td>\
+                 <td class=\"num\" data-sort=\"{wasted}\">{hwasted}</td></tr>",
+                hash = group.hash,
+                count = group.files.len(),
+                size = group.size,
+                hsize = ByteSize::b(group.size),
+                wasted = group.wasted_bytes,
+                hwasted = ByteSize::b(group.wasted_bytes),
+            )?;
+        }
+
+        writeln!(out, "</tbody>\n</table>\n<h2>Duplicates</h2>")?;
+        Ok(())
+    }
+
+    fn write_group(out: &mut impl Write, gindex: usize, group: &ReportGroup) -> Result<()> {
+        writeln!(
+            out,
+            "<details id=\"group-{gindex}\" open>\n<summary>{} &middot; {} files &middot; {} wasted</summary>",
+            group.hash,
+            group.files.len(),
+            ByteSize::b(group.wasted_bytes)
+        )?;
+        writeln!(
+            out,
+            "<table class=\"sortable\">\n<thead><tr><th>path</th><th class=\"num\">size</th>\
+             <th>modified</th></tr></thead>\n<tbody>"
+        )?;
+
+        for file in &group.files {
+            let path = Self::escape(&file.path);
+            let tag = match file.reference {
+                true => "<span class=\"tag\">reference</span>",
+                false => "",
+            };
+            writeln!(
+                out,
+                "<tr><td class=\"path\" data-sort=\"{path}\">{path}{tag}</td>\
+                 <td class=\"num\" data-sort=\"{size}\">{hsize}</td>\
+                 <td data-sort=\"{modified}\">{modified}</td></tr>",
+                size = file.size,
+                hsize = ByteSize::b(file.size),
+                modified = file.modified,
+            )?;
+        }
+
+        writeln!(out, "</tbody>\n</table>\n</details>")?;
+        Ok(())
+    }
+
+    fn escape(raw: &str) -> String {
+        raw.chars()
+            .fold(String::with_capacity(raw.len()), |mut acc, c| {
+                match c {
+                    '&' => acc.push_str("&amp;"),
+                    '<' => acc.push_str("&lt;"),
+                    '>' => acc.push_str("&gt;"),
+                    '"' => acc.push_str("&quot;"),
+                    '\'' => acc.push_str("&#39;"),
+                    _ => acc.push(c),
+                };
+                acc
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HtmlReport;
+    use crate::report::{ReportFile, ReportGroup};
+    use crate::stats::Summary;
+    use anyhow::Result;
+    use std::path::PathBuf;
+
+    #[test]
+    fn html_report_escapes_file_paths() -> Result<()> {
+        let group = ReportGroup {
+            hash: format!("{:032x}", 1),
+            size: 10,
+            wasted_bytes: 10,
+            files: ["/tmp/<script>alert(1)</script>", "/tmp/a&b"]
+                .iter()
+                .map(|path| ReportFile {
+                    path: path.to_string(),
+                    size: 10,
+                    modified: String::from("2024-01-01T00:00:00Z"),
+                    root: None,
+                    reference: path.contains('&'),
+                })
+                .collect(),
+        };
+
+        let mut out = vec![];
+        HtmlReport::write(
+            &mut out,
+            &[group],
+            &[PathBuf::from("/tmp")],
+            &Summary::default(),
+        )?;
+        let html = String::from_utf8(out)?;
+
+        assert!(!html.contains("<script>alert(1)"));
+        assert!(html.contains("/tmp/&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("/tmp/a&amp;b<span class=\"tag\">reference</span>"));
+
+        Ok(())
+    }
+}
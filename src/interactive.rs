@@ -1,8 +1,11 @@
+use crate::executor::{Action, Executor, Tally};
+use crate::hasher::Digest;
+use crate::journal::Journal;
+use crate::processor::GroupKey;
 use crate::{fileinfo::FileInfo, formatter::Formatter, params::Params};
 use anyhow::Result;
 use dashmap::DashMap;
 use prettytable::{format, row, Table};
-use std::sync::atomic::AtomicU64;
 use std::{
     io::{self, Write},
     sync::Arc,
@@ -11,21 +14,23 @@ use std::{
 pub struct Interactive;
 
 impl Interactive {
-    pub fn init(result: Arc<DashMap<u128, Vec<FileInfo>>>, app_args: &Params) -> Result<()> {
-        let store = result.clone();
-        if store.is_empty() {
+    pub fn init(
+        result: Arc<DashMap<GroupKey, Vec<FileInfo>>>,
+        app_args: &Params,
+        action: &Action,
+    ) -> Result<()> {
+        let journal = app_args.get_journal()?;
+        let roots = app_args.get_directories()?;
+        let mut tally = Tally::default();
+        let groups = Formatter::sorted_groups(&result, app_args.sort);
+        if groups.is_empty() {
             println!("No duplicates found matching your search criteria.");
         }
 
-        let printed_count: AtomicU64 = AtomicU64::new(0);
-
-        store
+        let outcome = groups
             .iter()
-            .filter(|i| i.value().len() > 1)
             .enumerate()
-            .for_each(|(gindex, i)| {
-                printed_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                let group = i.value();
+            .try_for_each(|(gindex, (hash, group))| {
                 let mut itable = Table::new();
                 itable.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
                 itable.set_titles(row!["index", "filename", "size", "updated_at"]);
@@ -37,22 +42,34 @@ impl Interactive {
                     .unwrap_or_default();
 
                 group.iter().enumerate().for_each(|(index, file)| {
-                    itable.add_row(row![
-                        index,
-                        Formatter::human_path(file, app_args, max_path_size).unwrap_or_default(),
-                        Formatter::human_filesize(file).unwrap_or_default(),
-                        Formatter::human_mtime(file).unwrap_or_default()
-                    ]);
+                    let path = Formatter::human_path(file, &roots, max_path_size);
+                    let size = Formatter::human_filesize(file).unwrap_or_default();
+                    let mtime = Formatter::human_mtime(file).unwrap_or_default();
+
+                    // NOTE: protected & reference files are shown, but can never be selected.
+                    match (file.reference, file.protected) {
+                        (true, _) => itable
+                            .add_row(row![Fc => format!("{index} (reference)"), path, size, mtime]),
+                        (false, true) => itable
+                            .add_row(row![Fy => format!("{index} (protected)"), path, size, mtime]),
+                        (false, false) => itable.add_row(row![index, path, size, mtime]),
+                    };
                 });
 
-                Self::process_group_action(group, gindex, result.len(), itable);
+                Self::process_group_action(
+                    action,
+                    &journal,
+                    &mut tally,
+                    *hash,
+                    group,
+                    gindex,
+                    groups.len(),
+                    itable,
+                )
             });
 
-        if printed_count.load(std::sync::atomic::Ordering::Relaxed) < 1 {
-            println!("No duplicates found matching your search criteria.");
-        }
-
-        Ok(())
+        Executor::print_tally(action, &tally);
+        outcome
     }
 
     pub fn scan_group_confirmation() -> Result<bool> {
@@ -67,8 +84,11 @@ impl Interactive {
         }
     }
 
-    pub fn scan_group_instruction() -> Result<String> {
-        println!("\nEnter the indices of the files you want to delete.");
+    pub fn scan_group_instruction(action: &Action) -> Result<String> {
+        println!(
+            "\nEnter the indices of the files you want {}.",
+            Executor::past_tense(action)
+        );
         println!("You can enter multiple files using commas to seperate file indices.");
         println!("example: 1,2");
         print!("\n> ");
@@ -79,15 +99,20 @@ impl Interactive {
         Ok(user_input)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn process_group_action(
+        action: &Action,
+        journal: &Journal,
+        tally: &mut Tally,
+        hash: Digest,
         duplicates: &Vec<FileInfo>,
         dup_index: usize,
         dup_size: usize,
         table: Table,
-    ) {
+    ) -> Result<()> {
         println!("\nDuplicate Set {} of {}\n", dup_index + 1, dup_size);
         table.printstd();
-        let files_to_delete = Self::scan_group_instruction().unwrap_or_default();
+        let files_to_delete = Self::scan_group_instruction(action).unwrap_or_default();
         let parsed_file_indices = files_to_delete
             .trim()
             .split(',')
@@ -101,20 +126,53 @@ impl Interactive {
             .any(|index| index > (duplicates.len() - 1))
         {
             println!("Err: File Index Out of Bounds!");
-            return Self::process_group_action(duplicates, dup_index, dup_size, table);
+            return Self::process_group_action(
+                action, journal, tally, hash, duplicates, dup_index, dup_size, table,
+            );
+        }
+
+        if let Some(index) = parsed_file_indices
+            .iter()
+            .find(|&&index| duplicates[index].protected || duplicates[index].reference)
+        {
+            let role = match duplicates[*index].reference {
+                true => "a reference file",
+                false => "protected",
+            };
+            println!("Err: File {index} is {role} and cannot be selected!");
+            return Self::process_group_action(
+                action, journal, tally, hash, duplicates, dup_index, dup_size, table,
+            );
         }
 
+        let unselected = (0..duplicates.len())
+            .filter(|index| !parsed_file_indices.contains(index))
+            .collect::<Vec<usize>>();
+        let Some(survivor) = unselected
+            .iter()
+            .min_by_key(|&&index| (!duplicates[index].reference, !duplicates[index].protected))
+            .map(|&index| &duplicates[index])
+        else {
+            println!("Err: At least one file of the set has to be kept!");
+            return Self::process_group_action(
+                action, journal, tally, hash, duplicates, dup_index, dup_size, table,
+            );
+        };
+
         print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
 
         if parsed_file_indices.is_empty() {
-            return;
+            return Ok(());
         }
 
         let files_to_delete = parsed_file_indices
             .into_iter()
             .map(|index| duplicates[index].clone());
 
-        println!("\nThe following files will be deleted:");
+        println!(
+            "\nThe following files will be {}:",
+            Executor::past_tense(action)
+        );
         files_to_delete
             .clone()
             .enumerate()
@@ -124,14 +182,15 @@ impl Interactive {
 
         match Self::scan_group_confirmation().unwrap() {
             true => {
-                files_to_delete.into_iter().for_each(|file| {
-                    match std::fs::remove_file(file.path.clone()) {
-                        Ok(_) => println!("DELETED: {}", file.path.display()),
-                        Err(_) => println!("FAILED: {}", file.path.display()),
-                    }
-                });
+                let files = files_to_delete.collect::<Vec<FileInfo>>();
+                if Executor::verify_group(survivor, &files, tally) {
+                    files.iter().try_for_each(|file| {
+                        Executor::apply_and_report(action, hash, file, survivor, journal, tally)
+                    })?;
+                }
             }
-            false => println!("\nCancelled Delete Operation."),
+            false => println!("\nCancelled Operation."),
         }
+        Ok(())
     }
 }
@@ -0,0 +1,328 @@
+use crate::executor::{Action, Executor, Skip};
+use crate::fileinfo::FileInfo;
+use crate::hasher::Digest;
+use crate::params::Params;
+use crate::quarantine::Quarantine;
+use crate::trash::Trash;
+use anyhow::{bail, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// append-only record of every action applied to a file, used by `undo`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Journal {
+    pub path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalAction {
+    Delete,
+    HardLink,
+    SymLink,
+    Reflink,
+    Quarantine,
+    Trash,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub id: String,
+    pub timestamp: String,
+    pub action: JournalAction,
+    pub original: PathBuf,
+    pub hash: String,
+    pub size: u64,
+    pub modified: String,
+    /// where the file went (quarantine & trash) or what it now points at (links).
+    pub destination: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum JournalRecord {
+    Applied(JournalEntry),
+    Undone { id: String, timestamp: String },
+}
+
+impl From<&Action> for JournalAction {
+    fn from(action: &Action) -> Self {
+        match action {
+            Action::Delete => JournalAction::Delete,
+            Action::HardLink => JournalAction::HardLink,
+            Action::SymLink(_) => JournalAction::SymLink,
+            Action::Reflink => JournalAction::Reflink,
+            Action::Quarantine(_) => JournalAction::Quarantine,
+            Action::Trash(_) => JournalAction::Trash,
+        }
+    }
+}
+
+impl Journal {
+    pub fn default_path() -> Result<PathBuf> {
+        let state_home = Params::xdg_home("XDG_STATE_HOME", ".local/state")?;
+        Ok(state_home.join("deduplicator/journal.jsonl"))
+    }
+
+    pub fn record(
+        &self,
+        action: &Action,
+        hash: Digest,
+        file: &FileInfo,
+        destination: Option<PathBuf>,
+    ) -> Result<()> {
+        let modified: DateTime<Utc> = file.modified.into();
+        let id: u64 = rand::rng().random();
+
+        self.append(&JournalRecord::Applied(JournalEntry {
+            id: format!("{id:016x}"),
+            timestamp: Self::now(),
+            action: action.into(),
+            original: file.path.to_path_buf(),
+            hash: hash.to_string(),
+            size: file.size,
+            modified: modified.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            destination,
+        }))
+    }
+
+    /// applied entries that have not been undone yet, oldest first.
+    pub fn pending(&self) -> Result<Vec<JournalEntry>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        let records = BufReader::new(fs::File::open(&self.path)?)
+            .lines()
+            .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<Result<Vec<JournalRecord>>>()?;
+
+        let undone = records
+            .iter()
+            .filter_map(|record| match record {
+                JournalRecord::Undone { id, .. } => Some(id.clone()),
+                JournalRecord::Applied(_) => None,
+            })
+            .collect::<HashSet<String>>();
+
+        Ok(records
+            .into_iter()
+            .filter_map(|record| match record {
+                JournalRecord::Applied(entry) if !undone.contains(&entry.id) => Some(entry),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// reverses journaled actions, newest first, and reports those that cannot be reversed.
+    pub fn undo(&self) -> Result<()> {
+        let (mut undone, mut irreversible, mut failed) = (0u64, 0u64, 0u64);
+
+        for entry in self.pending()?.iter().rev() {
+            match Self::reverse(entry) {
+                Ok(_) => {
+                    undone += 1;
+                    println!("UNDONE: {}", entry.original.display());
+                    self.append(&JournalRecord::Undone {
+                        id: entry.id.clone(),
+                        timestamp: Self::now(),
+                    })?;
+                }
+                Err(error) if error.is::<Skip>() => {
+                    irreversible += 1;
+                    println!("IRREVERSIBLE: {} ({error})", entry.original.display());
+                }
+                Err(error) => {
+                    failed += 1;
+                    println!("FAILED: {} ({error})", entry.original.display());
+                }
+            }
+        }
+
+        println!("\n{undone} actions undone, {irreversible} irreversible, {failed} failed.");
+        Ok(())
+    }
+
+    fn reverse(entry: &JournalEntry) -> Result<()> {
+        let destination = entry.destination.as_deref();
+        match (entry.action, destination) {
+            (JournalAction::Delete, _) => {
+                Err(Skip(String::from("deleted files cannot be recovered")).into())
+            }
+            // NOTE: reflinked files already are separate inodes with identical content.
+            (JournalAction::Reflink, _) => Ok(()),
+            (JournalAction::HardLink | JournalAction::SymLink, Some(survivor)) => {
+                Self::restore_copy(entry, survivor)
+            }
+            (JournalAction::Quarantine, Some(quarantined)) => {
+                Self::ensure_vacant(&entry.original)?;
+                Executor::move_file(quarantined, &entry.original)?;
+                Quarantine::forget(quarantined)
+            }
+            (JournalAction::Trash, Some(trashed)) => {
+                Self::ensure_vacant(&entry.original)?;
+                Trash::restore(trashed, &entry.original)
+            }
+            (_, None) => bail!("the journal does not record where the file went"),
+        }
+    }
+
+    /// turns a link back into an independent copy of the file it points at.
+    fn restore_copy(entry: &JournalEntry, survivor: &Path) -> Result<()> {
+        // NOTE: whatever replaced the link since is the user's, never overwrite it.
+        let meta = fs::symlink_metadata(&entry.original)?;
+        let still_linked = match entry.action {
+            JournalAction::SymLink => {
+                meta.file_type().is_symlink()
+                    && matches!(
+                        (fs::canonicalize(&entry.original), fs::canonicalize(survivor)),
+                        (Ok(target), Ok(survivor)) if target == survivor
+                    )
+            }
+            _ => {
+                !meta.file_type().is_symlink()
+                    && meta.len() == entry.size
+                    && FileInfo::identity(&meta) == FileInfo::identity(&fs::metadata(survivor)?)
+            }
+        };
+        if !still_linked {
+            bail!("file replaced since it was linked");
+        }
+
+        let modified = DateTime::parse_from_rfc3339(&entry.modified)?;
+        Executor::replace_atomically(&entry.original, |temp| {
+            fs::copy(survivor, temp)?;
+            fs::File::options()
+                .write(true)
+                .open(temp)?
+                .set_modified(modified.into())
+        })
+    }
+
+    fn ensure_vacant(path: &Path) -> Result<()> {
+        if path.symlink_metadata().is_ok() {
+            bail!("a file already exists at the original path");
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(())
+    }
+
+    fn append(&self, record: &JournalRecord) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut journal = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        writeln!(journal, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+
+    fn now() -> String {
+        Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Journal;
+    use crate::executor::{Action, Executor, Tally};
+    use crate::fileinfo::FileInfo;
+    use crate::hasher::Digest;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn undo_breaks_links_back_into_copies_but_not_deletions() -> Result<()> {
+        let root = TempDir::new()?;
+        let journal = Journal {
+            path: root.path().join("state/journal.jsonl"),
+        };
+        let files = FileInfo::create_copies(
+            root.path(),
+            &["kept.txt", "linked.txt", "deleted.txt"],
+            b"same content",
+        )?;
+
+        let mut tally = Tally::default();
+        Executor::apply_and_report(
+            &Action::HardLink,
+            Digest::from(1),
+            &files[1],
+            &files[0],
+            &journal,
+            &mut tally,
+        )?;
+        Executor::apply_and_report(
+            &Action::Delete,
+            Digest::from(1),
+            &files[2],
+            &files[0],
+            &journal,
+            &mut tally,
+        )?;
+        assert_eq!(journal.pending()?.len(), 2);
+
+        journal.undo()?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let (kept, linked) = (fs::metadata(&files[0].path)?, fs::metadata(&files[1].path)?);
+            assert_ne!(kept.ino(), linked.ino());
+        }
+        assert_eq!(fs::read(&files[1].path)?, b"same content");
+        assert_eq!(fs::metadata(&files[1].path)?.modified()?, files[1].modified);
+        assert!(!files[2].path.exists());
+
+        let pending = journal.pending()?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].original, files[2].path.to_path_buf());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn undo_leaves_files_replaced_since_linking_alone() -> Result<()> {
+        let root = TempDir::new()?;
+        let journal = Journal {
+            path: root.path().join("state/journal.jsonl"),
+        };
+        let files =
+            FileInfo::create_copies(root.path(), &["kept.txt", "linked.txt"], b"same content")?;
+
+        let mut tally = Tally::default();
+        Executor::apply_and_report(
+            &Action::HardLink,
+            Digest::from(1),
+            &files[1],
+            &files[0],
+            &journal,
+            &mut tally,
+        )?;
+
+        // NOTE: same size as the linked copy, only the inode tells them apart.
+        fs::remove_file(&files[1].path)?;
+        fs::write(&files[1].path, b"user's data!")?;
+
+        journal.undo()?;
+
+        assert_eq!(fs::read(&files[1].path)?, b"user's data!");
+        assert_eq!(journal.pending()?.len(), 1);
+
+        Ok(())
+    }
+}
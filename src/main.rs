@@ -1,33 +1,88 @@
+mod compare;
+mod config;
+mod executor;
 mod fileinfo;
 mod formatter;
+mod hasher;
+mod html;
 mod interactive;
+mod journal;
 mod params;
+mod plan;
 mod processor;
+mod quarantine;
+mod reflink;
+mod report;
 mod scanner;
+mod script;
 mod server;
+mod stats;
+mod trash;
 
-use self::{formatter::Formatter, interactive::Interactive, server::Server};
-use anyhow::Result;
+use self::{
+    executor::{Action, Executor},
+    formatter::Formatter,
+    interactive::Interactive,
+    server::Server,
+};
+use anyhow::{bail, Result};
 use clap::Parser;
-use params::Params;
+use compare::Comparison;
+use params::{Command, Params};
+use plan::Plan;
+use quarantine::Quarantine;
+use script::Script;
 use std::sync::atomic::Ordering;
 
 fn main() -> Result<()> {
     let app_args = Params::parse();
+    match &app_args.command {
+        Some(Command::Restore { directory, group }) => {
+            return Quarantine::restore(directory, group.as_deref())
+        }
+        Some(Command::Undo) => return app_args.get_journal()?.undo(),
+        Some(Command::Apply { plan }) => return Plan::load(plan)?.apply(&app_args),
+        Some(Command::Compare { a, b }) => return Comparison::run(a, b, &app_args),
+        Some(Command::Plan { .. }) | None => {}
+    }
+
+    let action = app_args.action()?;
+    if matches!(app_args.command, Some(Command::Plan { .. })) && action.is_none() {
+        bail!("a plan needs an action: --delete, --link, --quarantine or --trash");
+    }
+    if app_args.script.is_some() && action.is_none() && !app_args.interactive {
+        bail!("a script needs an action: --delete or --link");
+    }
+
     let server = Server::new(app_args.clone());
 
     server.start()?;
 
-    match app_args.interactive {
-        false => {
+    let sorted_groups = || Formatter::sorted_groups(&server.hw_duplicate_set, app_args.sort);
+    match (&app_args.command, action) {
+        (Some(Command::Plan { output }), Some(action)) => {
+            Plan::new(&sorted_groups(), action, &app_args)?.save(output)?;
+        }
+        (_, action) if app_args.interactive => {
+            Interactive::init(
+                server.hw_duplicate_set,
+                &app_args,
+                &action.unwrap_or(Action::Delete),
+            )?;
+        }
+        (_, Some(action)) if app_args.script.is_some() => {
+            Script::save(&sorted_groups(), &action, &app_args)?;
+        }
+        (_, Some(action)) => {
+            Executor::run(&sorted_groups(), &action, &app_args)?;
+        }
+        (_, None) => {
             Formatter::print(
                 server.hw_duplicate_set,
                 server.max_file_path_len.load(Ordering::Acquire),
                 &app_args,
-            );
-        }
-        true => {
-            Interactive::init(server.hw_duplicate_set, &app_args)?;
+                &server.stats,
+            )?;
         }
     };
 
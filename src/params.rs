@@ -1,20 +1,136 @@
 use std::{fs, path::PathBuf};
 
-use anyhow::Result;
-use clap::{Parser, ValueHint};
+use crate::config::Config;
+use crate::executor::Action;
+use crate::hasher::HashAlgorithm;
+use crate::journal::Journal;
+use crate::quarantine::Quarantine;
+use crate::trash::Trash;
+use anyhow::{anyhow, bail, Result};
+use clap::{Parser, Subcommand, ValueEnum, ValueHint};
+use serde::{Deserialize, Serialize};
+
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Colored tree of duplicate groups
+    #[default]
+    Tree,
+    /// Versioned JSON document of all duplicate groups
+    Json,
+    /// One JSON line per duplicate group, streamed as groups are confirmed (never before the scan completes)
+    Ndjson,
+    /// One comma separated row per duplicate file
+    Csv,
+    /// One tab separated row per duplicate file
+    Tsv,
+    /// Newline separated paths with a blank line between groups, like `fdupes -r`
+    Fdupes,
+    /// Self-contained static HTML report
+    Html,
+}
+
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Path of the first file in each group
+    #[default]
+    Path,
+    /// Reclaimable bytes per group, largest first
+    Wasted,
+    /// Size of the duplicated file, largest first
+    Size,
+    /// Number of files in each group, largest first
+    Count,
+    /// Modification time, oldest first
+    Mtime,
+}
+
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum KeepStrategy {
+    /// Most recently modified file
+    Newest,
+    /// Least recently modified file
+    #[default]
+    Oldest,
+    /// File with the shortest path
+    ShortestPath,
+    /// File with the longest path
+    LongestPath,
+    /// File under the first scanned root
+    FirstRoot,
+    /// File whose path sorts first
+    Lexicographic,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// Hardlink to the kept file (same filesystem only)
+    Hard,
+    /// Symbolic link to the kept file (see --symlink-target)
+    Sym,
+    /// Share extents with the kept file, keeping separate inodes (Linux, Btrfs/XFS)
+    Reflink,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkTarget {
+    /// Path of the kept file relative to the link's directory
+    #[default]
+    Relative,
+    /// Absolute path of the kept file
+    Absolute,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Move quarantined files back to where they came from
+    Restore {
+        /// Quarantine directory holding the manifest
+        #[arg(value_hint = ValueHint::DirPath)]
+        directory: PathBuf,
+        /// Only restore the group whose hash starts with this prefix
+        #[arg(long)]
+        group: Option<String>,
+    },
+    /// Reverse journaled actions, newest first
+    Undo,
+    /// Scan & write the groups, kept files & action to a plan file instead of acting
+    Plan {
+        /// Where to write the plan
+        #[arg(value_hint = ValueHint::FilePath)]
+        output: PathBuf,
+    },
+    /// Execute a plan file, skipping files that changed since it was made
+    Apply {
+        /// Plan written by the plan command
+        #[arg(value_hint = ValueHint::FilePath)]
+        plan: PathBuf,
+    },
+    /// Match the files of two trees by content & list those found in both, only in A or only in B
+    Compare {
+        /// The original tree
+        #[arg(value_hint = ValueHint::DirPath)]
+        a: PathBuf,
+        /// The tree it was copied to
+        #[arg(value_hint = ValueHint::DirPath)]
+        b: PathBuf,
+    },
+}
 
 #[derive(Parser, Debug, Default, Clone)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_precedence_over_arg = true)]
 pub struct Params {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     /// Exclude Filetypes [default = none]
     #[arg(short = 'T', long)]
     pub exclude_types: Option<String>,
     /// Filetypes to deduplicate [default = all]
     #[arg(short, long)]
     pub types: Option<String>,
-    /// Run Deduplicator on dir different from pwd (e.g., ~/Pictures )
+    /// Run Deduplicator on dirs different from pwd, duplicates are found across all of them (e.g., ~/Pictures /mnt/backup)
     #[arg(value_hint = ValueHint::DirPath, value_name = "scan_dir_path")]
-    pub dir: Option<PathBuf>,
+    pub dirs: Vec<PathBuf>,
     /// Delete files interactively
     #[arg(long, short)]
     pub interactive: bool,
@@ -33,12 +149,134 @@ pub struct Params {
     /// Guarantees that two files are duplicate (performs a full hash)
     #[arg(long, short = 's', default_value = "false")]
     pub strict: bool,
+    /// Digest used to group & report duplicates, all but gxhash always cover whole files (as with --strict)
+    #[arg(long, value_enum, default_value_t = HashAlgorithm::Gxhash)]
+    pub hash: HashAlgorithm,
     /// Show Progress spinners & metrics
     #[arg(long, short = 'p', default_value = "false")]
     pub progress: bool,
+    /// Output format of the duplicate report
+    #[arg(long, value_enum, default_value_t = OutputFormat::Tree)]
+    pub format: OutputFormat,
+    /// Print the size of duplicate files above each group (fdupes format only)
+    #[arg(long = "size", short = 'S', default_value = "false")]
+    pub show_size: bool,
+    /// Order of duplicate groups (and files within them) in the report
+    #[arg(long, value_enum, default_value_t = SortKey::Path)]
+    pub sort: SortKey,
+    /// Delete duplicates without prompting, keeping one file per group (see --keep)
+    #[arg(long, conflicts_with = "interactive")]
+    pub delete: bool,
+    /// Replace duplicates with links to the kept file instead of deleting them
+    #[arg(long, value_enum, conflicts_with_all = ["interactive", "delete"])]
+    pub link: Option<LinkKind>,
+    /// Move duplicates into this directory (mirroring their paths) instead of deleting them
+    #[arg(long, value_hint = ValueHint::DirPath, conflicts_with_all = ["delete", "link"])]
+    pub quarantine: Option<PathBuf>,
+    /// Move duplicates to the freedesktop.org trash so they can be restored from a file manager
+    #[arg(long, conflicts_with_all = ["delete", "link", "quarantine"])]
+    pub trash: bool,
+    /// How symbolic links created by --link sym refer to the kept file
+    #[arg(long, value_enum, default_value_t = SymlinkTarget::Relative)]
+    pub symlink_target: SymlinkTarget,
+    /// Write the --delete/--link commands to this POSIX shell script (`-` for stdout) instead of running them
+    #[arg(long, value_hint = ValueHint::FilePath, conflicts_with = "interactive")]
+    pub script: Option<PathBuf>,
+    /// Never delete, link or move files under this path (repeatable, merged with the config)
+    #[arg(long, value_hint = ValueHint::AnyPath)]
+    pub protect: Vec<PathBuf>,
+    /// Compare against the files under this dir (repeatable) without ever reporting or touching them
+    #[arg(long, value_hint = ValueHint::DirPath)]
+    pub reference: Vec<PathBuf>,
+    /// Config file [default: $XDG_CONFIG_HOME/deduplicator/config.toml]
+    #[arg(long, global = true, value_hint = ValueHint::FilePath)]
+    pub config: Option<PathBuf>,
+    /// Journal of applied actions [default: $XDG_STATE_HOME/deduplicator/journal.jsonl]
+    #[arg(long, global = true, value_hint = ValueHint::FilePath)]
+    pub journal: Option<PathBuf>,
+    /// Which file of each group survives non-interactive actions
+    #[arg(long, value_enum, default_value_t = KeepStrategy::Oldest)]
+    pub keep: KeepStrategy,
 }
 
 impl Params {
+    pub fn action(&self) -> Result<Option<Action>> {
+        if let Some(directory) = &self.quarantine {
+            return Ok(Some(Action::Quarantine(Quarantine {
+                directory: directory.clone(),
+                roots: self.get_directories()?,
+            })));
+        }
+
+        if self.trash {
+            return Ok(Some(Action::Trash(Trash::from_env()?)));
+        }
+
+        Ok(match (self.delete, self.link) {
+            (true, _) => Some(Action::Delete),
+            (false, Some(LinkKind::Hard)) => Some(Action::HardLink),
+            (false, Some(LinkKind::Sym)) => Some(Action::SymLink(self.symlink_target)),
+            (false, Some(LinkKind::Reflink)) => Some(Action::Reflink),
+            (false, None) => None,
+        })
+    }
+
+    pub fn get_journal(&self) -> Result<Journal> {
+        let path = match &self.journal {
+            Some(path) => path.clone(),
+            None => Journal::default_path()?,
+        };
+        Ok(Journal { path })
+    }
+
+    /// `--protect`ed paths along with those from the config, made absolute.
+    pub fn get_protected(&self) -> Result<Vec<PathBuf>> {
+        let config = match &self.config {
+            Some(path) if !path.exists() => bail!("config {} not found", path.display()),
+            Some(path) => Config::load(path)?,
+            // NOTE: without $XDG_CONFIG_HOME & $HOME there is no default config to read.
+            None => match Config::default_path() {
+                Ok(path) => Config::load(&path)?,
+                Err(_) => Config::default(),
+            },
+        };
+
+        let current_dir = std::env::current_dir()?;
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+
+        Ok(self
+            .protect
+            .iter()
+            .chain(config.protect.iter())
+            .map(|path| match (path.strip_prefix("~"), &home) {
+                (Ok(relative), Some(home)) => home.join(relative),
+                _ => current_dir.join(path),
+            })
+            .map(|path| fs::canonicalize(&path).unwrap_or(path))
+            .collect())
+    }
+
+    pub fn get_references(&self) -> Result<Vec<PathBuf>> {
+        self.reference
+            .iter()
+            .map(|path| Ok(fs::canonicalize(path)?))
+            .collect()
+    }
+
+    /// `$var` when set to an absolute path, `$HOME/fallback` otherwise.
+    pub fn xdg_home(var: &str, fallback: &str) -> Result<PathBuf> {
+        std::env::var_os(var)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))
+            .ok_or_else(|| anyhow!("unable to resolve ${var}, $HOME is not set"))
+    }
+
+    /// whether files are hashed in full, a partial digest would not match `sha256sum` & co.
+    pub fn full_hash(&self) -> bool {
+        self.strict || self.hash != HashAlgorithm::Gxhash
+    }
+
     pub fn get_min_size(&self) -> Option<u64> {
         match &self.min_size {
             Some(msize) => match msize.parse::<bytesize::ByteSize>() {
@@ -49,10 +287,55 @@ impl Params {
         }
     }
 
-    pub fn get_directory(&self) -> Result<PathBuf> {
-        let current_dir = std::env::current_dir()?;
-        let dir_path = self.dir.as_ref().unwrap_or(&current_dir).as_path();
-        let dir = fs::canonicalize(dir_path)?;
-        Ok(dir)
+    /// the canonical scan roots in the order given, the current dir when there are none.
+    pub fn get_directories(&self) -> Result<Vec<PathBuf>> {
+        if self.dirs.is_empty() {
+            return Ok(vec![fs::canonicalize(std::env::current_dir()?)?]);
+        }
+
+        let mut roots: Vec<PathBuf> = vec![];
+        for dir in &self.dirs {
+            let root = fs::canonicalize(dir)?;
+            if !roots.contains(&root) {
+                roots.push(root);
+            }
+        }
+        Ok(roots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, Params};
+    use clap::Parser;
+    use std::path::PathBuf;
+
+    #[test]
+    fn restore_subcommand_takes_precedence_over_the_scan_dir() {
+        let params = Params::parse_from(["deduplicator", "restore", "/tmp/q", "--group", "ab"]);
+
+        assert!(params.dirs.is_empty());
+        assert_eq!(
+            params.command,
+            Some(Command::Restore {
+                directory: PathBuf::from("/tmp/q"),
+                group: Some(String::from("ab")),
+            })
+        );
+        assert_eq!(Params::parse_from(["deduplicator", "/tmp"]).command, None);
+
+        let params = Params::parse_from(["deduplicator", "/tmp", "--delete", "plan", "p.json"]);
+        assert_eq!(params.dirs, vec![PathBuf::from("/tmp")]);
+        assert!(params.delete);
+        assert_eq!(
+            params.command,
+            Some(Command::Plan {
+                output: PathBuf::from("p.json")
+            })
+        );
+
+        let params = Params::parse_from(["deduplicator", "/a", "/b", "plan", "p.json"]);
+        assert_eq!(params.dirs, vec![PathBuf::from("/a"), PathBuf::from("/b")]);
+        assert!(matches!(params.command, Some(Command::Plan { .. })));
     }
 }
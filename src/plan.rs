@@ -0,0 +1,285 @@
+use crate::executor::{Action, Executor, Skip, Tally};
+use crate::fileinfo::FileInfo;
+use crate::formatter::DuplicateGroup;
+use crate::hasher::{Digest, HashAlgorithm};
+use crate::params::Params;
+use anyhow::{bail, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub const PLAN_VERSION: u32 = 2;
+
+/// the groups, survivors & action of a scan, to be applied later by `apply`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Plan {
+    pub version: u32,
+    pub created_at: String,
+    /// seed of the content hashes in every fingerprint.
+    pub seed: i64,
+    /// algorithm of the content hashes.
+    pub algorithm: HashAlgorithm,
+    pub action: Action,
+    pub groups: Vec<PlanGroup>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PlanGroup {
+    pub hash: String,
+    pub survivor: Fingerprint,
+    pub duplicates: Vec<Fingerprint>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: String,
+    pub hash: String,
+}
+
+impl Fingerprint {
+    fn new(file: &FileInfo, algorithm: HashAlgorithm, seed: i64) -> Result<Self> {
+        Ok(Self {
+            path: file.path.to_path_buf(),
+            size: file.size,
+            modified: Self::timestamp(file),
+            hash: file.hash(algorithm, seed)?.to_string(),
+        })
+    }
+
+    /// the file as it is now, provided it still matches the fingerprint.
+    fn verify(
+        &self,
+        algorithm: HashAlgorithm,
+        seed: i64,
+        protected: &[PathBuf],
+    ) -> Result<FileInfo> {
+        let file = FileInfo::new(self.path.clone())?.with_protection(protected);
+        let changed = if file.size != self.size {
+            "size"
+        } else if Self::timestamp(&file) != self.modified {
+            "modification time"
+        } else if file.hash(algorithm, seed)?.to_string() != self.hash {
+            "contents"
+        } else {
+            return Ok(file);
+        };
+
+        Err(Skip(format!("{changed} changed since the plan was made")).into())
+    }
+
+    fn timestamp(file: &FileInfo) -> String {
+        let modified: DateTime<Utc> = file.modified.into();
+        modified.to_rfc3339_opts(SecondsFormat::Nanos, true)
+    }
+}
+
+impl Plan {
+    pub fn new(groups: &[DuplicateGroup], action: Action, app_args: &Params) -> Result<Self> {
+        let roots = app_args.get_directories()?;
+        let seed: i64 = rand::rng().random();
+
+        let groups = groups
+            .par_iter()
+            .map(|(hash, files)| {
+                let survivor = Executor::select_survivor(files, app_args.keep, &roots);
+                Ok(PlanGroup {
+                    hash: hash.to_string(),
+                    survivor: Fingerprint::new(&files[survivor], app_args.hash, seed)?,
+                    duplicates: files
+                        .iter()
+                        .enumerate()
+                        .filter(|&(index, file)| index != survivor && !file.reference)
+                        .map(|(_, file)| Fingerprint::new(file, app_args.hash, seed))
+                        .collect::<Result<Vec<Fingerprint>>>()?,
+                })
+            })
+            .collect::<Result<Vec<PlanGroup>>>()?;
+
+        Ok(Self {
+            version: PLAN_VERSION,
+            created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            seed,
+            algorithm: app_args.hash,
+            action,
+            groups,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut out, self)?;
+        writeln!(out)?;
+        out.flush()?;
+
+        let duplicates: usize = self.groups.iter().map(|g| g.duplicates.len()).sum();
+        eprintln!(
+            "planned {} groups ({duplicates} files to be {}) in {}",
+            self.groups.len(),
+            Executor::past_tense(&self.action),
+            path.display()
+        );
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Versioned {
+            version: u32,
+        }
+
+        // NOTE: the version is checked first, older plans lack fields & would fail to parse.
+        let raw = fs::read_to_string(path)?;
+        let Versioned { version } = serde_json::from_str(&raw)?;
+        if version != PLAN_VERSION {
+            bail!(
+                "unsupported plan version {version} (expected {PLAN_VERSION}), \
+                 create a new one with `deduplicator plan`"
+            );
+        }
+        let plan: Self = serde_json::from_str(&raw)?;
+
+        // NOTE: an invalid hash must not surface halfway through applying the plan.
+        for group in &plan.groups {
+            group.hash.parse::<Digest>()?;
+            for fingerprint in std::iter::once(&group.survivor).chain(&group.duplicates) {
+                fingerprint.hash.parse::<Digest>()?;
+            }
+        }
+        Ok(plan)
+    }
+
+    /// executes the plan, skipping every file (or whole group) that no longer matches it.
+    pub fn apply(&self, app_args: &Params) -> Result<()> {
+        let journal = app_args.get_journal()?;
+        let protected = app_args.get_protected()?;
+        let mut tally = Tally::default();
+
+        let outcome = self.groups.iter().try_for_each(|group| {
+            let hash = group.hash.parse::<Digest>()?;
+            let survivor = match group.survivor.verify(self.algorithm, self.seed, &protected) {
+                Ok(survivor) => survivor,
+                Err(error) => {
+                    println!(
+                        "\nSKIPPED GROUP: {} (kept file: {error})",
+                        group.survivor.path.display()
+                    );
+                    group.duplicates.iter().for_each(|duplicate| {
+                        tally.skip(&duplicate.path, format!("kept file: {error}"))
+                    });
+                    return Ok(());
+                }
+            };
+            println!("\nKEPT: {}", survivor.path.display());
+
+            let mut files = vec![];
+            for duplicate in &group.duplicates {
+                match duplicate.verify(self.algorithm, self.seed, &protected) {
+                    Ok(file) => files.push(file),
+                    Err(error) => tally.skip(&duplicate.path, error.to_string()),
+                }
+            }
+
+            if !Executor::verify_group(&survivor, &files, &mut tally) {
+                return Ok(());
+            }
+            files.iter().try_for_each(|file| {
+                Executor::apply_and_report(
+                    &self.action,
+                    hash,
+                    file,
+                    &survivor,
+                    &journal,
+                    &mut tally,
+                )
+            })
+        });
+
+        Executor::print_tally(&self.action, &tally);
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Plan;
+    use crate::executor::Action;
+    use crate::fileinfo::FileInfo;
+    use crate::hasher::{Digest, HashAlgorithm};
+    use crate::params::{KeepStrategy, Params};
+    use anyhow::Result;
+    use std::fs::{self, File};
+    use tempfile::TempDir;
+
+    #[test]
+    fn apply_refuses_files_changed_since_the_plan() -> Result<()> {
+        let (root, state) = (TempDir::new()?, TempDir::new()?);
+        let files =
+            FileInfo::create_copies(root.path(), &["a.txt", "b.txt", "c.txt"], b"same content")?;
+
+        let params = Params {
+            dirs: vec![root.path().into()],
+            keep: KeepStrategy::Lexicographic,
+            hash: HashAlgorithm::Sha256,
+            journal: Some(state.path().join("journal.jsonl")),
+            ..Default::default()
+        };
+
+        let path = state.path().join("plan.json");
+        Plan::new(&[(Digest::from(1), files)], Action::Delete, &params)?.save(&path)?;
+        let plan = Plan::load(&path)?;
+        assert_eq!(plan.groups[0].duplicates.len(), 2);
+
+        // NOTE: same size, so only the content hash can tell.
+        fs::write(root.path().join("c.txt"), b"SAME CONTENT")?;
+        let modified = plan.groups[0].duplicates[1]
+            .modified
+            .parse::<chrono::DateTime<chrono::Utc>>()?;
+        File::options()
+            .write(true)
+            .open(root.path().join("c.txt"))?
+            .set_modified(modified.into())?;
+
+        plan.apply(&params)?;
+
+        assert!(root.path().join("a.txt").exists());
+        assert!(!root.path().join("b.txt").exists());
+        assert!(root.path().join("c.txt").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_plans_are_rejected_when_loaded() -> Result<()> {
+        let root = TempDir::new()?;
+        let path = root.path().join("plan.json");
+        fs::write(
+            &path,
+            r#"{"version":1,"created_at":"2024-01-01T00:00:00Z","seed":1,"action":"delete","groups":[]}"#,
+        )?;
+
+        let error = Plan::load(&path).unwrap_err();
+        assert!(
+            error.to_string().contains("unsupported plan version 1"),
+            "{error}"
+        );
+
+        let fingerprint = r#"{"path":"/a","size":1,"modified":"2024-01-01T00:00:00Z","hash":"ab"}"#;
+        fs::write(
+            &path,
+            format!(
+                r#"{{"version":2,"created_at":"2024-01-01T00:00:00Z","seed":1,"algorithm":"sha256",
+                "action":"delete","groups":[{{"hash":"aéb","survivor":{fingerprint},"duplicates":[]}}]}}"#
+            ),
+        )?;
+        let error = Plan::load(&path).unwrap_err();
+        assert!(error.to_string().contains("aéb is not a digest"), "{error}");
+
+        Ok(())
+    }
+}
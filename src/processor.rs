@@ -1,28 +1,39 @@
 use anyhow::Result;
 use dashmap::DashMap;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use rayon::iter::IntoParallelRefMutIterator;
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex, TryLockError, TryLockResult};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use unicode_segmentation::UnicodeSegmentation;
 
 use crate::fileinfo::FileInfo;
+use crate::hasher::Digest;
 use crate::params::Params;
+use crate::stats::Stats;
+
+/// size & content hash of a group. fast mode only hashes the first pages, so files of
+/// different sizes sharing them must never be keyed alike.
+pub type GroupKey = (u64, Digest);
 
 pub struct Processor {}
 
 impl Processor {
+    #[allow(clippy::too_many_arguments)]
     pub fn hashwise(
         app_args: Arc<Params>,
         sw_store: Arc<DashMap<u64, Vec<FileInfo>>>,
-        hw_store: Arc<DashMap<u128, Vec<FileInfo>>>,
+        hw_store: Arc<DashMap<GroupKey, Vec<FileInfo>>>,
         progress_bar_box: Arc<MultiProgress>,
         max_file_size: Arc<AtomicU64>,
         seed: i64,
         sw_sorting_finished: Arc<AtomicBool>,
+        group_sink: Option<Sender<(Digest, Vec<FileInfo>)>>,
+        stats: Arc<Stats>,
     ) -> Result<()> {
+        let started = Instant::now();
         let progress_bar = match app_args.progress {
             true => progress_bar_box.add(ProgressBar::new_spinner()),
             false => ProgressBar::hidden(),
@@ -33,7 +44,13 @@ impl Processor {
         progress_bar.enable_steady_tick(Duration::from_millis(50));
         progress_bar.set_message("files grouped by hash.");
 
+        // hashes produced per size bucket that have not been streamed to the sink yet.
+        let mut unstreamed: HashMap<u64, HashSet<Digest>> = HashMap::new();
+
         loop {
+            // NOTE: read before collecting keys. once sizewise is done, no bucket can grow
+            // anymore, so every group hashed from here on is final.
+            let sizing_finished = sw_sorting_finished.load(std::sync::atomic::Ordering::Acquire);
             let keys: Vec<u64> = sw_store
                 .clone()
                 .iter()
@@ -43,42 +60,125 @@ impl Processor {
                 .collect();
 
             if keys.is_empty() {
-                match sw_sorting_finished.load(std::sync::atomic::Ordering::Relaxed) {
+                match sizing_finished {
                     true => {
+                        Self::stream_groups(&group_sink, &hw_store, &mut unstreamed);
                         progress_bar.finish_with_message("files grouped by hash.");
+                        Stats::record_elapsed(&stats.hashwise_nanos, started);
                         break Ok(());
                     }
                     false => continue,
                 }
             } else {
-                keys.into_par_iter().for_each(|key| {
-                    let mut group: Vec<FileInfo> = sw_store.get(&key).unwrap().to_vec();
-                    if group.len() > 1 {
-                        group.par_iter_mut().for_each(|file| {
-                            progress_bar.inc(1);
-                            file.sw_processed();
-
-                            let fhash = match app_args.strict {
-                                true => file.hash(seed).expect("hashing file failed."),
-                                false => file.initpages_hash(seed).expect("hashing file failed."),
-                            };
-
-                            Self::compare_and_update_max_path_len(
-                                max_file_size.clone(),
-                                file.path.to_string_lossy().graphemes(true).count() as u64,
-                            );
-
-                            hw_store
-                                .entry(fhash)
-                                .and_modify(|fileset| fileset.push(file.clone()))
-                                .or_insert_with(|| vec![file.clone()]);
+                // NOTE: buckets hashed before sizewise finished are final by now.
+                if sizing_finished {
+                    let (pending, mut settled): (HashMap<_, _>, HashMap<_, _>) =
+                        std::mem::take(&mut unstreamed)
+                            .into_iter()
+                            .partition(|(size, _)| keys.contains(size));
+                    unstreamed = pending;
+                    Self::stream_groups(&group_sink, &hw_store, &mut settled);
+                }
+
+                let earlier = &unstreamed;
+                let hashed: Vec<(u64, Vec<Digest>)> = keys
+                    .into_par_iter()
+                    .map(|key| {
+                        let group: Vec<FileInfo> = sw_store.get(&key).unwrap().to_vec();
+                        let hashes = group
+                            .par_iter()
+                            .filter(|file| !file.is_sw_processed())
+                            .filter_map(|file| {
+                                progress_bar.inc(1);
+                                file.sw_processed();
+
+                                let hashed = match app_args.full_hash() {
+                                    true => file.hash(app_args.hash, seed),
+                                    false => file.initpages_hash(app_args.hash, seed),
+                                };
+
+                                // NOTE: files that vanish or become unreadable mid scan are
+                                // skipped (and counted) rather than aborting the whole run.
+                                let Ok(fhash) = hashed else {
+                                    Stats::incr(&stats.hash_failures);
+                                    return None;
+                                };
+                                Stats::incr(&stats.files_hashed);
+
+                                Self::compare_and_update_max_path_len(
+                                    max_file_size.clone(),
+                                    file.path.to_string_lossy().graphemes(true).count() as u64,
+                                );
+
+                                hw_store
+                                    .entry((file.size, fhash))
+                                    .and_modify(|fileset| fileset.push(file.clone()))
+                                    .or_insert_with(|| vec![file.clone()]);
+
+                                Some(fhash)
+                            })
+                            .collect::<Vec<Digest>>();
+
+                        // NOTE: once sizewise is done no bucket can grow, so its groups are
+                        // streamed as soon as it is hashed instead of after the whole batch.
+                        if sizing_finished {
+                            let mut bucket = earlier.get(&key).cloned().unwrap_or_default();
+                            bucket.extend(hashes.iter().copied());
+                            Self::stream_bucket(&group_sink, &hw_store, key, bucket);
+                        }
+
+                        (key, hashes)
+                    })
+                    .collect();
+
+                if group_sink.is_some() {
+                    hashed
+                        .into_iter()
+                        .for_each(|(key, hashes)| match sizing_finished {
+                            true => {
+                                unstreamed.remove(&key);
+                            }
+                            false => unstreamed.entry(key).or_default().extend(hashes),
                         });
-                    };
-                });
+                }
             }
         }
     }
 
+    fn stream_groups(
+        group_sink: &Option<Sender<(Digest, Vec<FileInfo>)>>,
+        hw_store: &DashMap<GroupKey, Vec<FileInfo>>,
+        unstreamed: &mut HashMap<u64, HashSet<Digest>>,
+    ) {
+        unstreamed
+            .drain()
+            .for_each(|(size, hashes)| Self::stream_bucket(group_sink, hw_store, size, hashes));
+    }
+
+    /// sends the duplicate groups of a fully hashed size bucket to the sink.
+    fn stream_bucket(
+        group_sink: &Option<Sender<(Digest, Vec<FileInfo>)>>,
+        hw_store: &DashMap<GroupKey, Vec<FileInfo>>,
+        size: u64,
+        hashes: HashSet<Digest>,
+    ) {
+        let Some(sink) = group_sink else {
+            return;
+        };
+
+        hashes.into_iter().for_each(|hash| {
+            let group = hw_store
+                .get(&(size, hash))
+                .map(|fileset| fileset.to_vec())
+                .unwrap_or_default();
+
+            if FileInfo::is_duplicate_group(&group) {
+                // NOTE: the receiving end going away must not abort hashing.
+                let _ = sink.send((hash, group));
+            }
+        });
+    }
+
     pub fn compare_and_update_max_path_len(current: Arc<AtomicU64>, next: u64) {
         if current.load(Ordering::Relaxed) < next {
             current.store(next, Ordering::Release);
@@ -91,7 +191,9 @@ impl Processor {
         store: Arc<DashMap<u64, Vec<FileInfo>>>,
         files: Arc<Mutex<Vec<FileInfo>>>,
         progress_bar_box: Arc<MultiProgress>,
+        stats: Arc<Stats>,
     ) -> Result<()> {
+        let started = Instant::now();
         let progress_bar = match app_args.progress {
             true => progress_bar_box.add(ProgressBar::new_spinner()),
             false => ProgressBar::hidden(),
@@ -103,10 +205,13 @@ impl Processor {
         progress_bar.set_message("files grouped by size");
 
         loop {
+            // NOTE: read before popping, an empty queue only means we are done if the scanner
+            // had already finished by then.
+            let scanning_finished = scanner_finished.load(std::sync::atomic::Ordering::Acquire);
             let fileopt: Option<FileInfo> = {
                 match files.try_lock() {
                     Ok(mut flist) => flist.pop(),
-                    TryLockResult::Err(TryLockError::WouldBlock) => None,
+                    TryLockResult::Err(TryLockError::WouldBlock) => continue,
                     _ => None,
                 }
             };
@@ -120,9 +225,10 @@ impl Processor {
                         .or_insert_with(|| vec![file]);
                     continue;
                 }
-                None => match scanner_finished.load(std::sync::atomic::Ordering::Relaxed) {
+                None => match scanning_finished {
                     true => {
                         progress_bar.finish_with_message("files grouped by size");
+                        Stats::record_elapsed(&stats.sizewise_nanos, started);
                         break Ok(());
                     }
                     false => continue,
@@ -141,10 +247,15 @@ mod tests {
     use std::fs::File;
     use std::io::Write;
     use std::sync::atomic::{AtomicBool, AtomicU64};
-    use std::sync::{Arc, Mutex};
+    use std::sync::{mpsc, Arc, Mutex};
     use tempfile::TempDir;
 
-    use crate::{fileinfo::FileInfo, params::Params};
+    use crate::{
+        fileinfo::FileInfo,
+        hasher::{Digest, HashAlgorithm},
+        params::Params,
+        stats::Stats,
+    };
 
     use super::Processor;
 
@@ -189,6 +300,7 @@ mod tests {
             dupstore.clone(),
             file_queue,
             Arc::new(MultiProgress::new()),
+            Arc::new(Stats::default()),
         )?;
 
         let args = Params {
@@ -204,6 +316,8 @@ mod tests {
             Arc::new(AtomicU64::new(32)),
             300,
             Arc::new(AtomicBool::new(true)),
+            None,
+            Arc::new(Stats::default()),
         )?;
 
         assert_eq!(hw_dupstore.len(), 2);
@@ -211,6 +325,42 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn hashwise_reports_full_digests_for_standard_algorithms() -> Result<()> {
+        let root = TempDir::new()?;
+        let files = FileInfo::create_copies(
+            root.path(),
+            &["fileone.bin", "filetwo.bin"],
+            &generate_bytes(65536),
+        )?;
+        let expected = files[0].hash(HashAlgorithm::Sha256, 0)?;
+
+        let dupstore = Arc::new(DashMap::new());
+        dupstore.insert(65536, files);
+        let hw_dupstore = Arc::new(DashMap::new());
+
+        let args = Params {
+            hash: HashAlgorithm::Sha256,
+            ..Default::default()
+        };
+
+        Processor::hashwise(
+            Arc::new(args),
+            dupstore,
+            hw_dupstore.clone(),
+            Arc::new(MultiProgress::new()),
+            Arc::new(AtomicU64::new(32)),
+            300,
+            Arc::new(AtomicBool::new(true)),
+            None,
+            Arc::new(Stats::default()),
+        )?;
+
+        assert!(hw_dupstore.contains_key(&(65536, expected)));
+
+        Ok(())
+    }
+
     #[test]
     fn hashwise_sorting_two_files_with_identical_init_pages_only_fast_mode() -> Result<()> {
         let root = TempDir::new()?;
@@ -247,6 +397,7 @@ mod tests {
             dupstore.clone(),
             file_queue,
             Arc::new(MultiProgress::new()),
+            Arc::new(Stats::default()),
         )?;
 
         Processor::hashwise(
@@ -257,6 +408,8 @@ mod tests {
             Arc::new(AtomicU64::new(32)),
             300,
             Arc::new(AtomicBool::new(true)),
+            None,
+            Arc::new(Stats::default()),
         )?;
 
         assert_eq!(hw_dupstore.len(), 1);
@@ -293,6 +446,7 @@ mod tests {
             dupstore.clone(),
             file_queue,
             Arc::new(MultiProgress::new()),
+            Arc::new(Stats::default()),
         )?;
 
         Processor::hashwise(
@@ -303,6 +457,8 @@ mod tests {
             Arc::new(AtomicU64::new(32)),
             300,
             Arc::new(AtomicBool::new(true)),
+            None,
+            Arc::new(Stats::default()),
         )?;
 
         assert_eq!(hw_dupstore.len(), 1);
@@ -310,6 +466,129 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn hashwise_streams_each_confirmed_group_once() -> Result<()> {
+        let root = TempDir::new()?;
+        let content = generate_bytes(282624);
+        let files = [
+            (root.path().join("fileone.bin"), content.clone()),
+            (root.path().join("filetwo.bin"), content.clone()),
+            (root.path().join("filethree.bin"), generate_bytes(282624)),
+        ];
+
+        for (fpath, content) in files.iter() {
+            let mut f = File::create_new(fpath)?;
+            f.write_all(content)?;
+        }
+
+        let dupstore = Arc::new(DashMap::new());
+        let file_queue = Arc::new(Mutex::new(
+            files
+                .iter()
+                .map(|f| FileInfo::new(f.0.clone()).unwrap())
+                .collect::<Vec<FileInfo>>(),
+        ));
+
+        let hw_dupstore = Arc::new(DashMap::new());
+        Processor::sizewise(
+            Arc::new(Params::default()),
+            Arc::new(AtomicBool::new(true)),
+            dupstore.clone(),
+            file_queue,
+            Arc::new(MultiProgress::new()),
+            Arc::new(Stats::default()),
+        )?;
+
+        let (sender, receiver) = mpsc::channel();
+        Processor::hashwise(
+            Arc::new(Params::default()),
+            dupstore.clone(),
+            hw_dupstore.clone(),
+            Arc::new(MultiProgress::new()),
+            Arc::new(AtomicU64::new(32)),
+            300,
+            Arc::new(AtomicBool::new(true)),
+            Some(sender),
+            Arc::new(Stats::default()),
+        )?;
+
+        let streamed = receiver.iter().collect::<Vec<(Digest, Vec<FileInfo>)>>();
+
+        assert_eq!(streamed.len(), 1);
+        assert_eq!(streamed[0].1.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn hashwise_never_groups_files_of_different_sizes_sharing_init_pages() -> Result<()> {
+        let root = TempDir::new()?;
+        let content = generate_bytes(16384);
+
+        let mut content_short = content.clone();
+        let mut content_long = content.clone();
+
+        content_short.extend(generate_bytes(4096));
+        content_long.extend(generate_bytes(8192));
+
+        let files = [
+            (root.path().join("short_one.bin"), content_short.clone()),
+            (root.path().join("short_two.bin"), content_short),
+            (root.path().join("long_one.bin"), content_long.clone()),
+            (root.path().join("long_two.bin"), content_long),
+        ];
+
+        for (fpath, content) in files.iter() {
+            let mut f = File::create_new(fpath)?;
+            f.write_all(content)?;
+        }
+
+        let dupstore = Arc::new(DashMap::new());
+        let file_queue = Arc::new(Mutex::new(
+            files
+                .iter()
+                .map(|f| FileInfo::new(f.0.clone()).unwrap())
+                .collect::<Vec<FileInfo>>(),
+        ));
+
+        let hw_dupstore = Arc::new(DashMap::new());
+        Processor::sizewise(
+            Arc::new(Params::default()),
+            Arc::new(AtomicBool::new(true)),
+            dupstore.clone(),
+            file_queue,
+            Arc::new(MultiProgress::new()),
+            Arc::new(Stats::default()),
+        )?;
+
+        let (sender, receiver) = mpsc::channel();
+        Processor::hashwise(
+            Arc::new(Params::default()),
+            dupstore.clone(),
+            hw_dupstore.clone(),
+            Arc::new(MultiProgress::new()),
+            Arc::new(AtomicU64::new(32)),
+            300,
+            Arc::new(AtomicBool::new(true)),
+            Some(sender),
+            Arc::new(Stats::default()),
+        )?;
+
+        // NOTE: fast mode hashes only the shared first pages, so all four hashes are equal.
+        assert_eq!(hw_dupstore.len(), 2);
+        assert!(hw_dupstore
+            .iter()
+            .all(|group| group.value().iter().all(|file| file.size == group.key().0)));
+
+        let streamed = receiver.iter().collect::<Vec<(Digest, Vec<FileInfo>)>>();
+        assert_eq!(streamed.len(), 2);
+        assert!(streamed
+            .iter()
+            .all(|(_, group)| group.len() == 2 && group[0].size == group[1].size));
+
+        Ok(())
+    }
+
     #[test]
     fn sizewise_sorting_two_files_of_different_sizes() -> Result<()> {
         let root = TempDir::new()?;
@@ -338,6 +617,7 @@ mod tests {
             dupstore.clone(),
             file_queue,
             Arc::new(MultiProgress::new()),
+            Arc::new(Stats::default()),
         )?;
 
         assert_eq!(dupstore.len(), 2);
@@ -373,6 +653,7 @@ mod tests {
             dupstore.clone(),
             file_queue,
             Arc::new(MultiProgress::new()),
+            Arc::new(Stats::default()),
         )?;
 
         assert_eq!(dupstore.len(), 1);
@@ -0,0 +1,217 @@
+use crate::executor::Executor;
+use crate::fileinfo::FileInfo;
+use crate::hasher::Digest;
+use anyhow::{bail, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Component, Path, PathBuf};
+
+const MANIFEST: &str = "manifest.jsonl";
+
+/// moves duplicates into `directory`, mirroring their path relative to the scanned root, or
+/// their absolute path when several roots were scanned.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Quarantine {
+    pub directory: PathBuf,
+    pub roots: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub group: String,
+    pub original: PathBuf,
+    /// relative to the quarantine directory.
+    pub quarantined: PathBuf,
+    pub size: u64,
+    pub modified: String,
+    pub quarantined_at: String,
+}
+
+impl Quarantine {
+    pub fn store(&self, file: &FileInfo, hash: Digest) -> Result<PathBuf> {
+        fs::create_dir_all(&self.directory)?;
+        let directory = fs::canonicalize(&self.directory)?;
+        if file.path.starts_with(&directory) {
+            bail!("file is already inside the quarantine directory");
+        }
+
+        let relative = Self::mirrored_path(&file.path, &self.roots);
+        let destination = directory.join(&relative);
+        if destination.symlink_metadata().is_ok() {
+            bail!("{} already exists in quarantine", relative.display());
+        }
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Executor::move_file(&file.path, &destination)?;
+
+        let modified: DateTime<Utc> = file.modified.into();
+        Self::append(
+            &directory,
+            &ManifestEntry {
+                group: hash.to_string(),
+                original: file.path.to_path_buf(),
+                quarantined: relative,
+                size: file.size,
+                modified: modified.to_rfc3339_opts(SecondsFormat::Secs, true),
+                quarantined_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            },
+        )?;
+
+        Ok(destination)
+    }
+
+    /// moves quarantined files (optionally only those of one group) back where they came from.
+    pub fn restore(directory: &Path, group: Option<&str>) -> Result<()> {
+        let entries = Self::entries(directory)?;
+        let (mut restored, mut failed) = (0u64, 0u64);
+        let mut remaining = vec![];
+
+        for entry in entries {
+            if group.is_some_and(|group| !entry.group.starts_with(group)) {
+                remaining.push(entry);
+                continue;
+            }
+
+            match Self::restore_entry(directory, &entry) {
+                Ok(_) => {
+                    restored += 1;
+                    println!("RESTORED: {}", entry.original.display());
+                }
+                Err(error) => {
+                    failed += 1;
+                    println!("FAILED: {} ({error})", entry.original.display());
+                    remaining.push(entry);
+                }
+            }
+        }
+
+        Self::rewrite(directory, &remaining)?;
+        println!("\n{restored} files restored, {failed} failed.");
+
+        Ok(())
+    }
+
+    /// drops the manifest entry of a file that was moved out of quarantine by other means.
+    pub fn forget(quarantined: &Path) -> Result<()> {
+        let Some(directory) = quarantined
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.join(MANIFEST).exists())
+        else {
+            return Ok(());
+        };
+
+        let remaining = Self::entries(directory)?
+            .into_iter()
+            .filter(|entry| directory.join(&entry.quarantined) != quarantined)
+            .collect::<Vec<ManifestEntry>>();
+
+        Self::rewrite(directory, &remaining)
+    }
+
+    fn restore_entry(directory: &Path, entry: &ManifestEntry) -> Result<()> {
+        if entry.original.symlink_metadata().is_ok() {
+            bail!("a file already exists at the original path");
+        }
+
+        if let Some(parent) = entry.original.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Executor::move_file(&directory.join(&entry.quarantined), &entry.original)
+    }
+
+    /// the path relative to the only root, or the absolute path minus its root otherwise, so
+    /// files from different roots never collide.
+    fn mirrored_path(path: &Path, roots: &[PathBuf]) -> PathBuf {
+        match roots {
+            [root] if path.starts_with(root) => path.strip_prefix(root).unwrap().to_path_buf(),
+            _ => path
+                .components()
+                .filter(|component| matches!(component, Component::Normal(_)))
+                .collect(),
+        }
+    }
+
+    pub fn entries(directory: &Path) -> Result<Vec<ManifestEntry>> {
+        let manifest = directory.join(MANIFEST);
+        if !manifest.exists() {
+            bail!("no quarantine manifest found in {}", directory.display());
+        }
+
+        BufReader::new(fs::File::open(manifest)?)
+            .lines()
+            .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+
+    fn append(directory: &Path, entry: &ManifestEntry) -> Result<()> {
+        let mut manifest = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(directory.join(MANIFEST))?;
+
+        writeln!(manifest, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    fn rewrite(directory: &Path, entries: &[ManifestEntry]) -> Result<()> {
+        let temp = directory.join(format!("{MANIFEST}.tmp"));
+        let mut manifest = fs::File::create(&temp)?;
+        for entry in entries {
+            writeln!(manifest, "{}", serde_json::to_string(entry)?)?;
+        }
+        manifest.sync_all()?;
+
+        fs::rename(temp, directory.join(MANIFEST))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Quarantine;
+    use crate::fileinfo::FileInfo;
+    use crate::hasher::Digest;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn quarantined_files_mirror_their_path_and_can_be_restored() -> Result<()> {
+        let (root, target) = (TempDir::new()?, TempDir::new()?);
+        let root_path = fs::canonicalize(root.path())?;
+        fs::create_dir_all(root_path.join("nested/dir"))?;
+
+        let files =
+            FileInfo::create_copies(&root_path, &["nested/dir/one.txt", "two.txt"], b"duplicate")?;
+
+        let quarantine = Quarantine {
+            directory: target.path().join("quarantine"),
+            roots: vec![root_path.clone()],
+        };
+        quarantine.store(&files[0], Digest::from(1))?;
+        quarantine.store(&files[1], Digest::from(2))?;
+
+        assert!(!files[0].path.exists());
+        assert!(quarantine.directory.join("nested/dir/one.txt").exists());
+        assert_eq!(Quarantine::entries(&quarantine.directory)?.len(), 2);
+
+        Quarantine::restore(&quarantine.directory, Some(&format!("{:032x}", 1)))?;
+
+        assert_eq!(fs::read(&files[0].path)?, b"duplicate");
+        assert!(!files[1].path.exists());
+        assert_eq!(Quarantine::entries(&quarantine.directory)?.len(), 1);
+
+        Quarantine::restore(&quarantine.directory, None)?;
+
+        assert!(files[1].path.exists());
+        assert!(Quarantine::entries(&quarantine.directory)?.is_empty());
+
+        Ok(())
+    }
+}
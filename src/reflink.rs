@@ -0,0 +1,111 @@
+use anyhow::Result;
+use std::path::Path;
+
+/// shares the extents of `source` with `dest` through the FIDEDUPERANGE ioctl. the kernel
+/// compares both ranges before sharing anything and `dest` keeps its own inode & metadata.
+pub struct Reflink;
+
+#[cfg(target_os = "linux")]
+mod ffi {
+    // NOTE: mirrors `struct file_dedupe_range` & `struct file_dedupe_range_info` from
+    // linux/fs.h, libc does not ship them.
+    #[repr(C)]
+    pub struct FileDedupeRange {
+        pub src_offset: u64,
+        pub src_length: u64,
+        pub dest_count: u16,
+        pub reserved1: u16,
+        pub reserved2: u32,
+    }
+
+    #[repr(C)]
+    pub struct FileDedupeRangeInfo {
+        pub dest_fd: i64,
+        pub dest_offset: u64,
+        pub bytes_deduped: u64,
+        pub status: i32,
+        pub reserved: u32,
+    }
+
+    #[repr(C)]
+    pub struct SingleDedupeRange {
+        pub range: FileDedupeRange,
+        pub info: FileDedupeRangeInfo,
+    }
+
+    pub const FIDEDUPERANGE: libc::Ioctl = libc::_IOWR::<FileDedupeRange>(0x94, 54);
+    pub const FILE_DEDUPE_RANGE_DIFFERS: i32 = 1;
+}
+
+impl Reflink {
+    /// some filesystems cap the length of a single dedupe request, so large files go in chunks.
+    #[cfg(target_os = "linux")]
+    const CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+
+    #[cfg(target_os = "linux")]
+    pub fn dedupe(source: &Path, dest: &Path, length: u64) -> Result<()> {
+        use anyhow::bail;
+        use std::fs::{File, OpenOptions};
+        use std::io::Error;
+        use std::os::fd::AsRawFd;
+
+        let source_file = File::open(source)?;
+        let dest_file = OpenOptions::new().write(true).open(dest)?;
+        let mut offset = 0u64;
+
+        while offset < length {
+            let mut request = ffi::SingleDedupeRange {
+                range: ffi::FileDedupeRange {
+                    src_offset: offset,
+                    src_length: (length - offset).min(Self::CHUNK_SIZE),
+                    dest_count: 1,
+                    reserved1: 0,
+                    reserved2: 0,
+                },
+                info: ffi::FileDedupeRangeInfo {
+                    dest_fd: dest_file.as_raw_fd() as i64,
+                    dest_offset: offset,
+                    bytes_deduped: 0,
+                    status: 0,
+                    reserved: 0,
+                },
+            };
+
+            // SAFETY: `request` is a `file_dedupe_range` followed by exactly `dest_count`
+            // info entries and outlives the call, both descriptors stay open until we return.
+            let result = unsafe {
+                libc::ioctl(
+                    source_file.as_raw_fd(),
+                    ffi::FIDEDUPERANGE,
+                    &mut request as *mut ffi::SingleDedupeRange,
+                )
+            };
+
+            if result < 0 {
+                let error = Error::last_os_error();
+                match error.raw_os_error() {
+                    Some(libc::EOPNOTSUPP | libc::ENOTTY | libc::EINVAL | libc::EXDEV) => {
+                        bail!("filesystem does not support reflinks ({error})")
+                    }
+                    _ => return Err(error.into()),
+                }
+            }
+
+            match request.info.status {
+                ffi::FILE_DEDUPE_RANGE_DIFFERS => bail!("contents differ from the kept file"),
+                status if status < 0 => {
+                    bail!("dedupe failed ({})", Error::from_raw_os_error(-status))
+                }
+                _ if request.info.bytes_deduped == 0 => bail!("dedupe made no progress"),
+                _ => offset += request.info.bytes_deduped,
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn dedupe(_source: &Path, _dest: &Path, _length: u64) -> Result<()> {
+        anyhow::bail!("only Linux filesystems support reflinks")
+    }
+}
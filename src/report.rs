@@ -0,0 +1,127 @@
+use crate::fileinfo::FileInfo;
+use crate::hasher::Digest;
+use crate::stats::Summary;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// bumped whenever the shape of the serialized report changes.
+pub const REPORT_VERSION: u32 = 1;
+
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub version: u32,
+    pub summary: Summary,
+    pub groups: Vec<ReportGroup>,
+}
+
+/// a single line of the streamed (ndjson) report.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ReportLine {
+    Header { version: u32 },
+    Group(ReportGroup),
+    Summary(Summary),
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReportGroup {
+    pub hash: String,
+    pub size: u64,
+    pub wasted_bytes: u64,
+    pub files: Vec<ReportFile>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReportFile {
+    pub path: String,
+    pub size: u64,
+    pub modified: String,
+    /// the scan root the file was found under, none for reference files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub reference: bool,
+}
+
+impl Report {
+    pub fn new(groups: Vec<ReportGroup>, summary: Summary) -> Self {
+        Self {
+            version: REPORT_VERSION,
+            summary,
+            groups,
+        }
+    }
+}
+
+impl ReportGroup {
+    pub fn new(hash: Digest, files: &[FileInfo], roots: &[PathBuf]) -> Self {
+        let size = files.first().map(|f| f.size).unwrap_or_default();
+        // NOTE: with a reference copy around, every other copy can go.
+        let (references, others): (Vec<&FileInfo>, Vec<&FileInfo>) =
+            files.iter().partition(|f| f.reference);
+        let total: u64 = others.iter().map(|f| f.size).sum::<u64>()
+            + references.first().map(|f| f.size).unwrap_or_default();
+
+        Self {
+            hash: hash.to_string(),
+            size,
+            wasted_bytes: total.saturating_sub(size),
+            files: files
+                .iter()
+                .map(|file| ReportFile::new(file, roots))
+                .collect(),
+        }
+    }
+
+    /// files that could go while keeping one copy, or every copy but the references.
+    pub fn duplicate_files(&self) -> u64 {
+        let references = self.files.iter().filter(|f| f.reference).count();
+        let others = self.files.len() - references;
+        match references {
+            0 => others.saturating_sub(1) as u64,
+            _ => others as u64,
+        }
+    }
+}
+
+impl ReportFile {
+    pub fn new(file: &FileInfo, roots: &[PathBuf]) -> Self {
+        let modified: DateTime<Utc> = file.modified.into();
+        Self {
+            path: file.path.to_string_lossy().to_string(),
+            size: file.size,
+            modified: modified.to_rfc3339_opts(SecondsFormat::Secs, true),
+            root: file
+                .root_index(roots)
+                .map(|index| roots[index].to_string_lossy().to_string()),
+            reference: file.reference,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn report_group_counts_every_copy_but_one_as_wasted() -> Result<()> {
+        let root = TempDir::new()?;
+        let files = FileInfo::create_copies(
+            root.path(),
+            &["one.bin", "two.bin", "three.bin"],
+            &[7u8; 1024],
+        )?;
+
+        let group = ReportGroup::new(Digest::from(0xabc), &files, &[]);
+
+        assert_eq!(group.hash, format!("{:032x}", 0xabc));
+        assert_eq!(group.size, 1024);
+        assert_eq!(group.wasted_bytes, 2048);
+        assert_eq!(group.files.len(), 3);
+
+        Ok(())
+    }
+}
@@ -1,13 +1,15 @@
-use crate::{fileinfo::FileInfo, params::Params};
+use crate::{fileinfo::FileInfo, params::Params, stats::Stats};
 use anyhow::Result;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::{path::Path, time::Duration};
+use std::time::{Duration, Instant};
 
 use globwalk::{GlobWalker, GlobWalkerBuilder};
 
 pub struct Scanner {
-    pub directory: Box<Path>,
+    pub directories: Vec<PathBuf>,
     pub min_depth: Option<usize>,
     pub max_depth: Option<usize>,
     pub include_types: Option<String>,
@@ -15,12 +17,14 @@ pub struct Scanner {
     pub min_size: Option<u64>,
     pub follow_links: bool,
     pub progress: bool,
+    pub protected: Vec<PathBuf>,
+    pub references: Vec<PathBuf>,
 }
 
 impl Scanner {
     pub fn new(app_args: Arc<Params>) -> Result<Self> {
         Ok(Self {
-            directory: app_args.get_directory()?.into_boxed_path(),
+            directories: app_args.get_directories()?,
             include_types: app_args.types.clone(),
             exclude_types: app_args.exclude_types.clone(),
             min_depth: app_args.min_depth,
@@ -28,6 +32,8 @@ impl Scanner {
             min_size: app_args.get_min_size(),
             follow_links: app_args.follow_links,
             progress: app_args.progress,
+            protected: app_args.get_protected()?,
+            references: app_args.get_references()?,
         })
     }
 
@@ -65,9 +71,9 @@ impl Scanner {
             None => Ok(walker),
         }
     }
-    fn build_walker(&self) -> Result<GlobWalker> {
+    fn build_walker(&self, directory: &Path) -> Result<GlobWalker> {
         let walker = Ok(GlobWalkerBuilder::from_patterns(
-            self.directory.clone(),
+            directory,
             &self.scan_patterns()?,
         ))
         .and_then(|walker| self.attach_walker_min_depth(walker))
@@ -81,7 +87,9 @@ impl Scanner {
         &self,
         files: Arc<Mutex<Vec<FileInfo>>>,
         progress_bar_box: Arc<MultiProgress>,
+        stats: Arc<Stats>,
     ) -> Result<()> {
+        let started = Instant::now();
         let progress_bar = match self.progress {
             true => progress_bar_box.add(ProgressBar::new_spinner()),
             false => ProgressBar::hidden(),
@@ -93,20 +101,65 @@ impl Scanner {
         progress_bar.set_message("paths mapped");
         let min_size = self.min_size.unwrap_or(0);
 
-        self.build_walker()?
-            .filter_map(Result::ok)
+        // NOTE: reference roots are walked like any other, overlapping roots yield each file once.
+        let walkers = self
+            .directories
+            .iter()
+            .chain(self.references.iter())
+            .map(|directory| self.build_walker(directory))
+            .collect::<Result<Vec<GlobWalker>>>()?;
+        let mut seen = HashSet::new();
+
+        walkers
+            .into_iter()
+            .flatten()
+            .filter_map(|entity| entity.inspect_err(|_| Stats::incr(&stats.walk_errors)).ok())
+            .inspect(|_entity| progress_bar.inc(1))
+            // NOTE: a symlink is not a copy of its target, never report them as duplicates
+            // unless asked to follow links.
+            .filter(|entity| {
+                let is_unfollowed_link = entity.path_is_symlink() && !self.follow_links;
+                if is_unfollowed_link {
+                    Stats::incr(&stats.not_regular_file);
+                }
+                !is_unfollowed_link
+            })
             .map(|entity| entity.into_path())
-            .inspect(|_path| progress_bar.inc(1))
-            .filter(|path| path.is_file())
+            .filter(|path| seen.insert(path.clone()))
+            .filter(|path| match path.is_file() {
+                true => true,
+                false => {
+                    if !path.is_dir() {
+                        Stats::incr(&stats.not_regular_file);
+                    }
+                    false
+                }
+            })
             .map(FileInfo::new)
-            .filter_map(Result::ok)
-            .filter(|file| file.size >= min_size)
+            .filter_map(|file| file.inspect_err(|_| Stats::incr(&stats.unreadable)).ok())
+            .map(|file| {
+                file.with_protection(&self.protected)
+                    .with_references(&self.references)
+            })
+            .filter(|file| match file.size >= min_size {
+                true => true,
+                false => {
+                    Stats::incr(&stats.below_min_size);
+                    false
+                }
+            })
             .for_each(|file| {
+                Stats::incr(&stats.files_scanned);
+                stats
+                    .bytes_scanned
+                    .fetch_add(file.size, std::sync::atomic::Ordering::Relaxed);
+
                 let mut flock = files.lock().unwrap();
                 flock.push(file);
             });
 
         progress_bar.finish_with_message("paths mapped");
+        Stats::record_elapsed(&stats.scan_nanos, started);
         Ok(())
     }
 }
@@ -115,6 +168,7 @@ impl Scanner {
 mod tests {
     use crate::fileinfo::FileInfo;
     use crate::params::Params;
+    use crate::stats::Stats;
     use std::fs::File;
     use std::sync::{Arc, Mutex};
 
@@ -141,7 +195,7 @@ mod tests {
 
         let params = Params {
             types: Some(String::from("js,csv")),
-            dir: Some(root.path().into()),
+            dirs: vec![root.path().into()],
             ..Default::default()
         };
 
@@ -150,7 +204,7 @@ mod tests {
         let scanner = Scanner::new(Arc::new(params)).expect("scanner initialization failed");
 
         scanner
-            .scan(scanlist.clone(), progress)
+            .scan(scanlist.clone(), progress, Arc::new(Stats::default()))
             .expect("scanning failed.");
 
         let scan_list_mg = scanlist.lock().unwrap();
@@ -187,7 +241,7 @@ mod tests {
 
         let params = Params {
             exclude_types: Some(String::from("js,csv")),
-            dir: Some(root.path().into()),
+            dirs: vec![root.path().into()],
             ..Default::default()
         };
 
@@ -196,7 +250,7 @@ mod tests {
         let scanner = Scanner::new(Arc::new(params)).expect("scanner initialization failed");
 
         scanner
-            .scan(scanlist.clone(), progress)
+            .scan(scanlist.clone(), progress, Arc::new(Stats::default()))
             .expect("scanning failed.");
 
         let scan_list_mg = scanlist.lock().unwrap();
@@ -234,7 +288,7 @@ mod tests {
         let params = Params {
             types: Some(String::from("js,csv,rs")),
             exclude_types: Some(String::from("csv")),
-            dir: Some(root.path().into()),
+            dirs: vec![root.path().into()],
             ..Default::default()
         };
 
@@ -243,7 +297,7 @@ mod tests {
         let scanner = Scanner::new(Arc::new(params)).expect("scanner initialization failed");
 
         scanner
-            .scan(scanlist.clone(), progress)
+            .scan(scanlist.clone(), progress, Arc::new(Stats::default()))
             .expect("scanning failed.");
 
         let scan_list_mg = scanlist.lock().unwrap();
@@ -257,4 +311,48 @@ mod tests {
         assert!(scan_list_mg.iter().any(|f| f.path.to_str().unwrap()
             == root.path().join("this-is-a-rust-file.rs").to_str().unwrap()));
     }
+
+    #[test]
+    fn reference_roots_are_scanned_and_flagged_once() {
+        let (root, archive) = (
+            TempDir::new().expect("unable to create tempdir"),
+            TempDir::new().expect("unable to create tempdir"),
+        );
+        File::create_new(root.path().join("incoming.txt")).expect("unable to create file");
+        File::create_new(archive.path().join("archived.txt")).expect("unable to create file");
+
+        let params = Params {
+            dirs: vec![root.path().into()],
+            reference: vec![archive.path().into(), archive.path().into()],
+            min_size: Some(String::from("0b")),
+            ..Default::default()
+        };
+
+        let scanlist = Arc::new(Mutex::<Vec<FileInfo>>::new(vec![]));
+        Scanner::new(Arc::new(params))
+            .expect("scanner initialization failed")
+            .scan(
+                scanlist.clone(),
+                Arc::new(MultiProgress::new()),
+                Arc::new(Stats::default()),
+            )
+            .expect("scanning failed.");
+
+        let mut files = scanlist.lock().unwrap().clone();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(
+            files.iter().filter(|file| file.reference).count(),
+            1,
+            "{files:?}"
+        );
+        assert!(FileInfo::is_duplicate_group(&files));
+        assert!(!FileInfo::is_duplicate_group(
+            &files
+                .into_iter()
+                .filter(|file| file.reference)
+                .collect::<Vec<_>>()
+        ));
+    }
 }
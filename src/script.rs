@@ -0,0 +1,334 @@
+use crate::executor::{Action, Executor};
+use crate::fileinfo::FileInfo;
+use crate::formatter::DuplicateGroup;
+use crate::params::Params;
+use anyhow::{anyhow, bail, Result};
+use chrono::Local;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const PRELUDE: &str = r#"set -u
+
+# verify <path> <size> <cksum>: the file is still a regular file with the scanned contents.
+verify() {
+    if [ ! -f "$1" ] || [ -L "$1" ]; then
+        echo "SKIPPED: $1 (missing or not a regular file)" >&2
+        return 1
+    fi
+    if [ "$(cksum < "$1")" != "$3 $2" ]; then
+        echo "SKIPPED: $1 (changed since the scan)" >&2
+        return 1
+    fi
+}
+"#;
+
+/// CRC table of the POSIX `cksum` polynomial, most significant bit first.
+const CKSUM_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = (index as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 0x8000_0000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x04C1_1DB7,
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+/// writes the commands an action would run as a POSIX shell script for review.
+pub struct Script;
+
+impl Script {
+    /// writes the script to `--script`, or stdout for `-`.
+    pub fn save(groups: &[DuplicateGroup], action: &Action, app_args: &Params) -> Result<()> {
+        let Some(path) = &app_args.script else {
+            bail!("no script path given");
+        };
+
+        if path.as_os_str() == "-" {
+            return Self::write(std::io::stdout().lock(), groups, action, app_args);
+        }
+
+        Self::write(
+            BufWriter::new(File::create(path)?),
+            groups,
+            action,
+            app_args,
+        )?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+        }
+
+        eprintln!("wrote {} to review & run", path.display());
+        Ok(())
+    }
+
+    pub fn write(
+        mut out: impl Write,
+        groups: &[DuplicateGroup],
+        action: &Action,
+        app_args: &Params,
+    ) -> Result<()> {
+        if !matches!(
+            action,
+            Action::Delete | Action::HardLink | Action::SymLink(_)
+        ) {
+            bail!("scripts only support --delete, --link hard and --link sym");
+        }
+
+        let roots = app_args.get_directories()?;
+        writeln!(out, "#!/bin/sh")?;
+        writeln!(
+            out,
+            "# generated by deduplicator {} on {}",
+            env!("CARGO_PKG_VERSION"),
+            Local::now().format("%Y-%m-%d %H:%M:%S")
+        )?;
+        writeln!(
+            out,
+            "# review before running: every command first re-checks the size & checksum of\n\
+             # both the kept file and the duplicate, and is skipped if either changed.\n"
+        )?;
+        writeln!(out, "{PRELUDE}")?;
+
+        for (hash, files) in groups {
+            let survivor = &files[Executor::select_survivor(files, app_args.keep, &roots)];
+            let (crc, size) = Self::cksum(&survivor.path)?;
+
+            let others = files
+                .iter()
+                .filter(|file| file.path != survivor.path && !file.reference)
+                .collect::<Vec<&FileInfo>>();
+
+            writeln!(out, "# group {hash}: {} files of {size} bytes", files.len())?;
+
+            // NOTE: fast mode only hashes the first pages, so compare every byte before scripting.
+            if let Err(error) = others
+                .iter()
+                .filter(|file| !file.protected && !Executor::same_file(file, survivor))
+                .try_for_each(|file| Executor::verify_identical(&survivor.path, &file.path))
+            {
+                writeln!(out, "# ABORTED: group left untouched ({error})\n")?;
+                continue;
+            }
+
+            writeln!(
+                out,
+                "if verify {} {size} {crc}; then",
+                Self::quote(&survivor.path)?
+            )?;
+
+            for file in others {
+                let path = Self::quote(&file.path)?;
+                if file.protected {
+                    writeln!(out, "    # PROTECTED: {path}")?;
+                    continue;
+                }
+                if Executor::same_file(file, survivor) {
+                    writeln!(
+                        out,
+                        "    # SKIPPED: {path} is the kept file under another path"
+                    )?;
+                    continue;
+                }
+
+                let command = match action {
+                    Action::HardLink => {
+                        format!("ln -f -- {} {path}", Self::quote(&survivor.path)?)
+                    }
+                    Action::SymLink(target) => format!(
+                        "ln -sf -- {} {path}",
+                        Self::quote(&Executor::link_target(file, survivor, *target)?)?
+                    ),
+                    _ => format!("rm -f -- {path}"),
+                };
+
+                writeln!(out, "    verify {path} {size} {crc} && {command}")?;
+            }
+
+            writeln!(out, "fi\n")?;
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+
+    /// checksum & length as printed by POSIX `cksum`.
+    pub fn cksum(path: &Path) -> Result<(u32, u64)> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut buffer = [0u8; 65536];
+        let (mut crc, mut length) = (0u32, 0u64);
+
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            crc = buffer[..read]
+                .iter()
+                .fold(crc, |crc, byte| Self::crc_step(crc, *byte));
+            length += read as u64;
+        }
+
+        let mut remaining = length;
+        while remaining > 0 {
+            crc = Self::crc_step(crc, remaining as u8);
+            remaining >>= 8;
+        }
+
+        Ok((!crc, length))
+    }
+
+    fn crc_step(crc: u32, byte: u8) -> u32 {
+        (crc << 8) ^ CKSUM_TABLE[((crc >> 24) as u8 ^ byte) as usize]
+    }
+
+    /// single quotes a path for the shell.
+    fn quote(path: &Path) -> Result<String> {
+        let raw = path
+            .to_str()
+            .ok_or_else(|| anyhow!("{} is not valid UTF-8", path.display()))?;
+        Ok(format!("'{}'", raw.replace('\'', r"'\''")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Script;
+    use crate::executor::Action;
+    use crate::fileinfo::FileInfo;
+    use crate::hasher::Digest;
+    use crate::params::{KeepStrategy, Params};
+    use anyhow::Result;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn cksum_matches_the_posix_utility() -> Result<()> {
+        let root = TempDir::new()?;
+        let (empty, digits) = (root.path().join("empty"), root.path().join("digits"));
+        File::create_new(&empty)?;
+        File::create_new(&digits)?.write_all(b"123456789")?;
+
+        assert_eq!(Script::cksum(&empty)?, (4294967295, 0));
+        assert_eq!(Script::cksum(&digits)?, (930766865, 9));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn script_only_removes_files_that_still_match() -> Result<()> {
+        let root = TempDir::new()?;
+        let files = FileInfo::create_copies(
+            root.path(),
+            &["a.txt", "it's b.txt", "c.txt"],
+            b"same content",
+        )?;
+
+        let params = Params {
+            dirs: vec![root.path().into()],
+            keep: KeepStrategy::Lexicographic,
+            ..Default::default()
+        };
+
+        let script = root.path().join("dedupe.sh");
+        Script::write(
+            File::create(&script)?,
+            &[(Digest::from(1), files)],
+            &Action::Delete,
+            &params,
+        )?;
+        fs::write(root.path().join("c.txt"), b"edited after the scan")?;
+
+        std::process::Command::new("sh").arg(&script).status()?;
+
+        assert!(root.path().join("a.txt").exists());
+        assert!(!root.path().join("it's b.txt").exists());
+        assert!(root.path().join("c.txt").exists());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_kept_file_is_never_scripted_under_another_path() -> Result<()> {
+        let root = TempDir::new()?;
+        fs::create_dir(root.path().join("real"))?;
+        std::os::unix::fs::symlink("real", root.path().join("link"))?;
+        fs::write(root.path().join("real/x.bin"), b"only copy")?;
+        let files = [
+            root.path().join("link/x.bin"),
+            root.path().join("real/x.bin"),
+        ]
+        .into_iter()
+        .map(FileInfo::new)
+        .collect::<Result<Vec<FileInfo>>>()?;
+
+        let params = Params {
+            dirs: vec![root.path().into()],
+            keep: KeepStrategy::Lexicographic,
+            ..Default::default()
+        };
+
+        let mut script = vec![];
+        Script::write(
+            &mut script,
+            &[(Digest::from(1), files)],
+            &Action::Delete,
+            &params,
+        )?;
+        let script = String::from_utf8(script)?;
+
+        assert!(
+            script.contains("is the kept file under another path"),
+            "{script}"
+        );
+        assert!(!script.contains("rm -f"), "{script}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn groups_differing_past_the_first_pages_are_left_out() -> Result<()> {
+        let root = TempDir::new()?;
+        let mut files = FileInfo::create_copies(root.path(), &["a.bin", "b.bin"], &[0u8; 65536])?;
+        // NOTE: same size, only the last byte differs.
+        fs::write(&files[1].path, [&[0u8; 65535][..], b"x"].concat())?;
+        files[1] = FileInfo::new(files[1].path.to_path_buf())?;
+
+        let params = Params {
+            dirs: vec![root.path().into()],
+            ..Default::default()
+        };
+
+        let mut script = vec![];
+        Script::write(
+            &mut script,
+            &[(Digest::from(1), files)],
+            &Action::Delete,
+            &params,
+        )?;
+        let script = String::from_utf8(script)?;
+
+        assert!(
+            script.contains("# ABORTED: group left untouched"),
+            "{script}"
+        );
+        assert!(!script.contains("rm -f"), "{script}");
+
+        Ok(())
+    }
+}
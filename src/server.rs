@@ -1,7 +1,10 @@
 use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
 
-use crate::processor::Processor;
+use crate::formatter::Formatter;
+use crate::params::OutputFormat;
+use crate::processor::{GroupKey, Processor};
 use crate::scanner::Scanner;
 use anyhow::Result;
 use dashmap::DashMap;
@@ -11,14 +14,16 @@ use threadpool::ThreadPool;
 
 use crate::fileinfo::FileInfo;
 use crate::params::Params;
+use crate::stats::Stats;
 
 pub struct Server {
     filequeue: Arc<Mutex<Vec<FileInfo>>>,
-    sw_duplicate_set: Arc<DashMap<u64, Vec<FileInfo>>>,
-    pub hw_duplicate_set: Arc<DashMap<u128, Vec<FileInfo>>>,
+    pub sw_duplicate_set: Arc<DashMap<u64, Vec<FileInfo>>>,
+    pub hw_duplicate_set: Arc<DashMap<GroupKey, Vec<FileInfo>>>,
     threadpool: ThreadPool,
     app_args: Arc<Params>,
     pub max_file_path_len: Arc<AtomicU64>,
+    pub stats: Arc<Stats>,
 }
 
 impl Server {
@@ -30,6 +35,7 @@ impl Server {
             threadpool: ThreadPool::new(4),
             app_args: Arc::new(opts),
             max_file_path_len: Arc::new(AtomicU64::new(0)),
+            stats: Arc::new(Stats::default()),
         }
     }
 
@@ -67,19 +73,40 @@ impl Server {
             Arc::clone(&self.hw_duplicate_set),
         );
         let max_file_path_len = Arc::clone(&self.max_file_path_len);
+        let (stats_sc, stats_sw, stats_hw) = (
+            Arc::clone(&self.stats),
+            Arc::clone(&self.stats),
+            Arc::clone(&self.stats),
+        );
+        let group_sink = match self.app_args.format {
+            OutputFormat::Ndjson => {
+                let (sender, receiver) = mpsc::channel();
+                let sort = self.app_args.sort;
+                let roots = self.app_args.get_directories()?;
+                let stats = Arc::clone(&self.stats);
+                self.threadpool.execute(move || {
+                    Formatter::print_ndjson(receiver, sort, roots, stats)
+                        .expect("streaming output failed.");
+                });
+
+                Some(sender)
+            }
+            _ => None,
+        };
         let (prog_sc, prog_sw, prog_hw) = (
             Arc::clone(&progbarbox),
             Arc::clone(&progbarbox),
             Arc::clone(&progbarbox),
         );
 
+        // NOTE: built up front, a scanner that cannot start would otherwise leave sizewise waiting.
+        let scanner = Scanner::new(app_args_sc)?;
         self.threadpool.execute(move || {
-            Scanner::new(app_args_sc)
-                .expect("unable to initialize scanner.")
-                .scan(file_queue_sc, prog_sc)
+            scanner
+                .scan(file_queue_sc, prog_sc, stats_sc)
                 .expect("scanner failed.");
 
-            sfin_sc.store(true, std::sync::atomic::Ordering::Relaxed);
+            sfin_sc.store(true, std::sync::atomic::Ordering::Release);
         });
 
         self.threadpool.execute(move || {
@@ -89,10 +116,11 @@ impl Server {
                 store_sw,
                 file_queue_pr,
                 prog_sw,
+                stats_sw,
             )
             .expect("sizewise scanner failed.");
 
-            swfin_pr_sw.store(true, std::sync::atomic::Ordering::Relaxed);
+            swfin_pr_sw.store(true, std::sync::atomic::Ordering::Release);
         });
 
         self.threadpool.execute(move || {
@@ -104,6 +132,8 @@ impl Server {
                 max_file_path_len,
                 seed,
                 swfin_pr_hw,
+                group_sink,
+                stats_hw,
             )
             .expect("sizewise scanner failed.");
         });
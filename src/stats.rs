@@ -0,0 +1,178 @@
+use crate::report::ReportGroup;
+use bytesize::ByteSize;
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// counters shared by the scanner & processor threads while a scan is running.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub files_scanned: AtomicU64,
+    pub bytes_scanned: AtomicU64,
+    pub walk_errors: AtomicU64,
+    pub unreadable: AtomicU64,
+    pub below_min_size: AtomicU64,
+    pub not_regular_file: AtomicU64,
+    pub hash_failures: AtomicU64,
+    pub files_hashed: AtomicU64,
+    pub scan_nanos: AtomicU64,
+    pub sizewise_nanos: AtomicU64,
+    pub hashwise_nanos: AtomicU64,
+}
+
+impl Stats {
+    pub fn incr(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_elapsed(stage: &AtomicU64, started: Instant) {
+        stage.store(started.elapsed().as_nanos() as u64, Ordering::Release);
+    }
+
+    fn load(counter: &AtomicU64) -> u64 {
+        counter.load(Ordering::Acquire)
+    }
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub files_scanned: u64,
+    pub bytes_scanned: u64,
+    pub files_skipped: SkippedSummary,
+    pub files_hashed: u64,
+    pub groups: u64,
+    /// files that could be removed while keeping one copy per group.
+    pub duplicate_files: u64,
+    pub reclaimable_bytes: u64,
+    pub elapsed_ms: ElapsedSummary,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct SkippedSummary {
+    pub walk_errors: u64,
+    pub unreadable: u64,
+    pub below_min_size: u64,
+    pub not_regular_file: u64,
+    pub hash_failures: u64,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct ElapsedSummary {
+    pub scan: u64,
+    pub sizewise: u64,
+    pub hashwise: u64,
+}
+
+impl Summary {
+    pub fn new(stats: &Stats, groups: &[ReportGroup]) -> Self {
+        let millis =
+            |stage: &AtomicU64| Duration::from_nanos(Stats::load(stage)).as_millis() as u64;
+
+        Self {
+            files_scanned: Stats::load(&stats.files_scanned),
+            bytes_scanned: Stats::load(&stats.bytes_scanned),
+            files_skipped: SkippedSummary {
+                walk_errors: Stats::load(&stats.walk_errors),
+                unreadable: Stats::load(&stats.unreadable),
+                below_min_size: Stats::load(&stats.below_min_size),
+                not_regular_file: Stats::load(&stats.not_regular_file),
+                hash_failures: Stats::load(&stats.hash_failures),
+            },
+            files_hashed: Stats::load(&stats.files_hashed),
+            groups: groups.len() as u64,
+            duplicate_files: groups.iter().map(ReportGroup::duplicate_files).sum(),
+            reclaimable_bytes: groups.iter().map(|g| g.wasted_bytes).sum(),
+            elapsed_ms: ElapsedSummary {
+                scan: millis(&stats.scan_nanos),
+                sizewise: millis(&stats.sizewise_nanos),
+                hashwise: millis(&stats.hashwise_nanos),
+            },
+        }
+    }
+}
+
+impl SkippedSummary {
+    pub fn total(&self) -> u64 {
+        self.walk_errors
+            + self.unreadable
+            + self.below_min_size
+            + self.not_regular_file
+            + self.hash_failures
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let skipped = &self.files_skipped;
+        writeln!(f, "Summary")?;
+        writeln!(
+            f,
+            "  files scanned:     {} ({})",
+            self.files_scanned,
+            ByteSize::b(self.bytes_scanned)
+        )?;
+        writeln!(
+            f,
+            "  files skipped:     {} ({} below min size, {} unreadable, {} walk errors, {} not regular files, {} hash failures)",
+            skipped.total(),
+            skipped.below_min_size,
+            skipped.unreadable,
+            skipped.walk_errors,
+            skipped.not_regular_file,
+            skipped.hash_failures
+        )?;
+        writeln!(f, "  files hashed:      {}", self.files_hashed)?;
+        writeln!(f, "  duplicate groups:  {}", self.groups)?;
+        writeln!(f, "  duplicate files:   {}", self.duplicate_files)?;
+        writeln!(
+            f,
+            "  reclaimable:       {}",
+            ByteSize::b(self.reclaimable_bytes)
+        )?;
+        write!(
+            f,
+            "  elapsed:           scan {}ms, sizewise {}ms, hashwise {}ms",
+            self.elapsed_ms.scan, self.elapsed_ms.sizewise, self.elapsed_ms.hashwise
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Stats, Summary};
+    use crate::report::{ReportFile, ReportGroup};
+
+    fn group(count: u64, size: u64) -> ReportGroup {
+        ReportGroup {
+            hash: String::new(),
+            size,
+            wasted_bytes: size * (count - 1),
+            files: (0..count)
+                .map(|_| ReportFile {
+                    path: String::new(),
+                    size,
+                    modified: String::new(),
+                    root: None,
+                    reference: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn summary_counts_one_copy_per_group_as_the_original() {
+        let stats = Stats::default();
+        Stats::incr(&stats.files_scanned);
+        Stats::incr(&stats.below_min_size);
+        Stats::incr(&stats.walk_errors);
+
+        let summary = Summary::new(&stats, &[group(3, 100), group(2, 50)]);
+
+        assert_eq!(summary.files_scanned, 1);
+        assert_eq!(summary.files_skipped.total(), 2);
+        assert_eq!(summary.groups, 2);
+        assert_eq!(summary.duplicate_files, 3);
+        assert_eq!(summary.reclaimable_bytes, 250);
+    }
+}
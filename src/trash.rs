@@ -0,0 +1,262 @@
+use crate::executor::Executor;
+use crate::params::Params;
+use anyhow::{anyhow, bail, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// moves files into the freedesktop.org trash, see
+/// https://specifications.freedesktop.org/trash-spec/latest/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Trash {
+    /// the home trash, usually `$XDG_DATA_HOME/Trash`.
+    pub home: PathBuf,
+}
+
+impl Trash {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            home: Params::xdg_home("XDG_DATA_HOME", ".local/share")?.join("Trash"),
+        })
+    }
+
+    /// trashes `path` & returns where it ended up.
+    pub fn send(&self, path: &Path) -> Result<PathBuf> {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            bail!("unable to determine the parent directory");
+        };
+        // NOTE: only the directory is resolved, a symlink is trashed itself, never its target.
+        let path = fs::canonicalize(parent)?.join(name);
+        Self::ensure_layout(&self.home)?;
+
+        // NOTE: the spec has files from other mounts go to that mount's trash, the home trash
+        // is only a (copying) fallback when no such trash can be used.
+        if !Self::same_device(&path, &self.home)? {
+            if let Some((trash, topdir)) = Self::topdir_trash(&path) {
+                let relative = path.strip_prefix(&topdir).unwrap_or(&path);
+                return Self::move_into(&trash, &path, relative);
+            }
+        }
+
+        Self::move_into(&self.home, &path, &path)
+    }
+
+    /// moves a trashed file back to `original` and drops its `.trashinfo`.
+    pub fn restore(trashed: &Path, original: &Path) -> Result<()> {
+        let (Some(files), Some(name)) = (trashed.parent(), trashed.file_name()) else {
+            bail!("{} is not inside a trash directory", trashed.display());
+        };
+        let info = files
+            .with_file_name("info")
+            .join(format!("{}.trashinfo", name.to_string_lossy()));
+
+        Executor::move_file(trashed, original)?;
+        let _ = fs::remove_file(info);
+
+        Ok(())
+    }
+
+    fn move_into(trash: &Path, path: &Path, recorded: &Path) -> Result<PathBuf> {
+        let (info_path, destination) = Self::reserve_name(trash, path, recorded)?;
+
+        if let Err(error) = Executor::move_file(path, &destination) {
+            let _ = fs::remove_file(info_path);
+            return Err(error);
+        }
+
+        Ok(destination)
+    }
+
+    /// claims a unique name by creating its `.trashinfo` first, as the spec requires.
+    fn reserve_name(trash: &Path, path: &Path, recorded: &Path) -> Result<(PathBuf, PathBuf)> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("unable to determine the file name"))?
+            .to_string_lossy();
+
+        let info = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            Self::encode(recorded),
+            Local::now().format("%Y-%m-%dT%H:%M:%S")
+        );
+
+        for attempt in 1.. {
+            let candidate = match attempt {
+                1 => name.to_string(),
+                n => format!("{name}.{n}"),
+            };
+
+            let info_path = trash.join("info").join(format!("{candidate}.trashinfo"));
+            let destination = trash.join("files").join(&candidate);
+            if destination.symlink_metadata().is_ok() {
+                continue;
+            }
+
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&info_path)
+            {
+                Ok(mut file) => {
+                    file.write_all(info.as_bytes())?;
+                    return Ok((info_path, destination));
+                }
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        unreachable!()
+    }
+
+    /// percent-encodes everything but unreserved characters & path separators (RFC 2396).
+    fn encode(path: &Path) -> String {
+        path.to_string_lossy()
+            .bytes()
+            .fold(String::new(), |mut acc, byte| {
+                match byte {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => acc.push(byte as char),
+                    b'/' | b'-' | b'_' | b'.' | b'~' | b'!' | b'*' | b'\'' | b'(' | b')' => {
+                        acc.push(byte as char)
+                    }
+                    _ => acc.push_str(&format!("%{byte:02X}")),
+                }
+                acc
+            })
+    }
+
+    fn ensure_layout(trash: &Path) -> Result<()> {
+        for dir in ["files", "info"] {
+            fs::create_dir_all(trash.join(dir))?;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(trash, fs::Permissions::from_mode(0o700))?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn same_device(a: &Path, b: &Path) -> Result<bool> {
+        use std::os::unix::fs::MetadataExt;
+        Ok(fs::symlink_metadata(a)?.dev() == fs::metadata(b)?.dev())
+    }
+
+    #[cfg(not(unix))]
+    fn same_device(_: &Path, _: &Path) -> Result<bool> {
+        Ok(true)
+    }
+
+    /// `$topdir/.Trash/$uid` when an admin set up a sticky `.Trash`, else `$topdir/.Trash-$uid`.
+    #[cfg(unix)]
+    fn topdir_trash(path: &Path) -> Option<(PathBuf, PathBuf)> {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let dev = fs::symlink_metadata(path).ok()?.dev();
+        let topdir = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| fs::metadata(dir).is_ok_and(|meta| meta.dev() == dev))
+            .last()?
+            .to_path_buf();
+
+        // SAFETY: getuid never fails.
+        let uid = unsafe { libc::getuid() };
+
+        let shared = topdir.join(".Trash");
+        let sticky = |meta: &fs::Metadata| meta.permissions().mode() & 0o1000 != 0;
+        let shared_is_usable =
+            fs::symlink_metadata(&shared).is_ok_and(|meta| meta.is_dir() && sticky(&meta));
+        let candidates = match shared_is_usable {
+            true => vec![
+                shared.join(uid.to_string()),
+                topdir.join(format!(".Trash-{uid}")),
+            ],
+            false => vec![topdir.join(format!(".Trash-{uid}"))],
+        };
+
+        candidates
+            .into_iter()
+            .find(|trash| {
+                Self::ensure_layout(trash).is_ok()
+                    && fs::symlink_metadata(trash).is_ok_and(|meta| meta.is_dir())
+            })
+            .map(|trash| (trash, topdir))
+    }
+
+    #[cfg(not(unix))]
+    fn topdir_trash(_: &Path) -> Option<(PathBuf, PathBuf)> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Trash;
+    use anyhow::Result;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn trashed_files_get_a_trashinfo_and_unique_names() -> Result<()> {
+        let root = TempDir::new()?;
+        let trash = Trash {
+            home: root.path().join("Trash"),
+        };
+
+        let path = fs::canonicalize(root.path())?.join("my file.txt");
+        for _ in 0..2 {
+            File::create_new(&path)?.write_all(b"duplicate")?;
+            trash.send(&path)?;
+        }
+
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read(trash.home.join("files/my file.txt"))?,
+            b"duplicate"
+        );
+        assert!(trash.home.join("files/my file.txt.2").exists());
+
+        let info = fs::read_to_string(trash.home.join("info/my file.txt.trashinfo"))?;
+        let mut lines = info.lines();
+        assert_eq!(lines.next(), Some("[Trash Info]"));
+        assert_eq!(
+            lines.next().map(String::from),
+            Some(format!("Path={}", path.display()).replace(' ', "%20"))
+        );
+        assert!(lines
+            .next()
+            .is_some_and(|line| line.starts_with("DeletionDate=")));
+        assert!(trash.home.join("info/my file.txt.2.trashinfo").exists());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_trashed_instead_of_their_targets() -> Result<()> {
+        let root = TempDir::new()?;
+        let trash = Trash {
+            home: root.path().join("Trash"),
+        };
+
+        let target = root.path().join("outside.bin");
+        fs::write(&target, b"duplicate")?;
+        let link = root.path().join("link.bin");
+        std::os::unix::fs::symlink(&target, &link)?;
+
+        let trashed = trash.send(&link)?;
+
+        assert!(link.symlink_metadata().is_err());
+        assert_eq!(fs::read(&target)?, b"duplicate");
+        assert!(trashed.symlink_metadata()?.file_type().is_symlink());
+
+        Ok(())
+    }
+}